@@ -40,6 +40,11 @@ namespace CodeWalkerCli.Utils
 
         public static void WriteMessage(MessageType type, string message)
         {
+            if (Protocol.Enabled)
+            {
+                Protocol.Log(type.ToString().ToLower(), message);
+                return;
+            }
             Write($"[{type.ToString().ToUpper()}]", Colors[type]);
             Console.WriteLine($" {message}");
         }
@@ -265,6 +265,7 @@ namespace CodeWalkerCli.Processing
                         
                         processedCount++;
                         ConsoleUtils.WriteInfo($"Processed model {processedCount}/{modelNames.Length}: {name.Trim()}");
+                        Protocol.Progress(processedCount, modelNames.Length, name.Trim());
                     }
                     catch (Exception ex)
                     {
@@ -55,6 +55,11 @@ namespace CodeWalkerCli
 
         private static int RunPipedMode()
         {
+            if (Protocol.Enabled)
+            {
+                return RunJsonMode();
+            }
+
             Console.SetOut(new StreamWriter(Console.OpenStandardOutput()) { AutoFlush = false });
             Console.WriteLine("[CLI] Running in piped mode");
             Console.Out.Flush();
@@ -100,6 +105,68 @@ namespace CodeWalkerCli
             return 0;
         }
 
+        private static int RunJsonMode()
+        {
+            Console.SetOut(new StreamWriter(Console.OpenStandardOutput()) { AutoFlush = false });
+            Protocol.Ready();
+
+            string? line;
+            while ((line = Console.ReadLine()) != null)
+            {
+                if (string.IsNullOrWhiteSpace(line)) continue;
+
+                if (!Protocol.TryReadRequest(line, out var requestId, out var command, out var error))
+                {
+                    if (requestId is long rejected)
+                    {
+                        Protocol.Start(rejected);
+                        ConsoleUtils.WriteError(error);
+                        Protocol.Finish(1);
+                    }
+                    else
+                    {
+                        ConsoleUtils.WriteError($"{error}: {line}");
+                    }
+                    continue;
+                }
+
+                Protocol.Start(requestId!.Value);
+                int code;
+                try
+                {
+                    code = parser.ParseArguments<ImportOptions, ExportOptions, HelloOptions, ExportModelOptions>(SplitCommandLine(command))
+                        .MapResult(
+                            (ImportOptions opts) => HandleImport(opts),
+                            (ExportOptions opts) => HandleExport(opts),
+                            (HelloOptions opts) => HandleHello(opts),
+                            (ExportModelOptions opts) => HandleExportModel(opts),
+                            errs => errs.All(e => e is HelpRequestedError || e is HelpVerbRequestedError) ? 0 : 1);
+                }
+                catch (Exception ex)
+                {
+                    ConsoleUtils.WriteError(ex.Message);
+                    code = 1;
+                }
+                Protocol.Finish(code);
+            }
+
+            return 0;
+        }
+
+        /// <summary>Sends a command's result: between output markers, or as the JSON result payload.</summary>
+        private static void WriteOutput(string content)
+        {
+            if (Protocol.Enabled)
+            {
+                Protocol.SetPayload(content);
+                return;
+            }
+            Console.WriteLine("[OUTPUT_START]");
+            Console.WriteLine(content);
+            Console.WriteLine("[OUTPUT_END]");
+            Console.Out.Flush();
+        }
+
         private static int RunInteractiveMode()
         {
             ConsoleUtils.WriteInfo("Interactive CLI mode. Type 'exit' to quit.");
@@ -193,10 +260,7 @@ namespace CodeWalkerCli
 
                 var (xmlContent, format) = processor.ExportXml(opts.InputPath, opts.OutputPath, opts.MetaFormat);
         
-                Console.WriteLine("[OUTPUT_START]");
-                Console.WriteLine(xmlContent);
-                Console.WriteLine("[OUTPUT_END]");
-                Console.Out.Flush();
+                WriteOutput(xmlContent);
                 ConsoleUtils.WriteCMD("Command completed");
                 return 0;
             }
@@ -209,7 +273,7 @@ namespace CodeWalkerCli
         
         private static int HandleHello(HelloOptions opts)
         {
-            Console.WriteLine($"Hello, {opts.Name}!");
+            WriteOutput($"Hello, {opts.Name}!");
             return 0;
         }
         
@@ -223,10 +287,7 @@ namespace CodeWalkerCli
                 {
                     File.WriteAllText(opts.OutputPath, data);
                 }
-                Console.WriteLine("[OUTPUT_START]");
-                Console.WriteLine(data);
-                Console.WriteLine("[OUTPUT_END]");
-                Console.Out.Flush();
+                WriteOutput(data);
                 return 0;
             }
             catch (Exception ex)
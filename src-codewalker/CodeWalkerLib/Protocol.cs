@@ -0,0 +1,143 @@
+using System.Text;
+using System.Text.Json;
+
+namespace CodeWalkerCli.Utils
+{
+    /// <summary>
+    /// JSON-lines protocol used in piped mode when CLI_PROTOCOL=jsonl. Every line written to stdout
+    /// is one JSON object with the protocol version "v" and a "type"; requests are read as
+    /// {"v": 1, "id": 1, "command": "exportxml -i ..."}. Without the variable the old marker output is used.
+    /// </summary>
+    public static class Protocol
+    {
+        public const int Version = 1;
+
+        public static bool Enabled { get; } = Environment.GetEnvironmentVariable("CLI_PROTOCOL") == "jsonl";
+
+        // The request being handled, so progress and errors can be attributed to it.
+        private static long? currentId;
+        private static string? payload;
+        private static readonly List<string> errors = new();
+
+        private static void WriteMessage(string type, Action<Utf8JsonWriter> fields)
+        {
+            using var stream = new MemoryStream();
+            using (var writer = new Utf8JsonWriter(stream))
+            {
+                writer.WriteStartObject();
+                writer.WriteNumber("v", Version);
+                writer.WriteString("type", type);
+                fields(writer);
+                writer.WriteEndObject();
+            }
+            Console.WriteLine(Encoding.UTF8.GetString(stream.ToArray()));
+            Console.Out.Flush();
+        }
+
+        public static void Ready() => WriteMessage("ready", _ => { });
+
+        public static void Log(string level, string message)
+        {
+            if (level == "error")
+            {
+                errors.Add(message);
+            }
+            WriteMessage("log", writer =>
+            {
+                if (currentId is long id) writer.WriteNumber("id", id);
+                writer.WriteString("level", level);
+                writer.WriteString("message", message);
+            });
+        }
+
+        public static void Progress(int done, int total, string message)
+        {
+            if (currentId is not long id) return;
+            WriteMessage("progress", writer =>
+            {
+                writer.WriteNumber("id", id);
+                writer.WriteNumber("done", done);
+                writer.WriteNumber("total", total);
+                writer.WriteString("message", message);
+            });
+        }
+
+        /// <summary>Sets the result of the current request; sent when the command finishes.</summary>
+        public static void SetPayload(string content) => payload = content;
+
+        /// <summary>
+        /// Parses a request line. Returns false with the reason in <paramref name="error"/> if it isn't a
+        /// valid request for this protocol version; <paramref name="id"/> is still set when the line had
+        /// one, so the request can be failed instead of left waiting.
+        /// </summary>
+        public static bool TryReadRequest(string line, out long? id, out string command, out string error)
+        {
+            id = null;
+            command = "";
+            error = "";
+            try
+            {
+                using var doc = JsonDocument.Parse(line);
+                var root = doc.RootElement;
+                if (root.TryGetProperty("id", out var idElement))
+                {
+                    id = idElement.GetInt64();
+                }
+                if (!root.TryGetProperty("v", out var versionElement) || !versionElement.TryGetInt32(out var version))
+                {
+                    error = "Request has no protocol version";
+                    return false;
+                }
+                if (version != Version)
+                {
+                    error = $"Unsupported protocol version {version}, expected {Version}";
+                    return false;
+                }
+                if (id == null || !root.TryGetProperty("command", out var commandElement))
+                {
+                    error = "Invalid request";
+                    return false;
+                }
+                command = commandElement.GetString() ?? "";
+                return true;
+            }
+            catch (Exception ex)
+            {
+                error = $"Invalid request: {ex.Message}";
+                return false;
+            }
+        }
+
+        public static void Start(long id)
+        {
+            currentId = id;
+            payload = null;
+            errors.Clear();
+            WriteMessage("started", writer => writer.WriteNumber("id", id));
+        }
+
+        /// <summary>
+        /// Sends the result of the current request. It counts as failed when the handler returned a
+        /// non-zero code, or logged errors without producing any output.
+        /// </summary>
+        public static void Finish(int code)
+        {
+            if (currentId is not long id) return;
+            var failed = code != 0 || (string.IsNullOrEmpty(payload) && errors.Count > 0);
+            WriteMessage("result", writer =>
+            {
+                writer.WriteNumber("id", id);
+                writer.WriteString("status", failed ? "error" : "ok");
+                if (failed)
+                {
+                    writer.WriteString("error", errors.Count > 0 ? string.Join("; ", errors) : $"Command failed with code {code}");
+                }
+                else
+                {
+                    writer.WriteString("payload", payload ?? "");
+                }
+            });
+            currentId = null;
+        }
+    }
+}
@@ -1,53 +1,194 @@
 mod utils {
+    pub mod cli_protocol;
     pub mod codewalkercli;
+    pub mod config_diff;
+    pub mod duplicates;
     pub mod files;
+    pub mod history;
+    pub mod lua_lexer;
+    pub mod lua_parser;
+    pub mod lua_sandbox;
+    pub mod lua_writer;
+    pub mod path_access;
+    pub mod quarantine;
+    pub mod resources;
+    pub mod scan;
     pub mod vector;
+    pub mod vector_export;
+    pub mod vector_index;
+    pub mod vector_transform;
 }
 
 use rayon::prelude::*;
-use std::path::Path;
-use utils::codewalkercli::{send_command, start_codewalker, stop_codewalker, validate_gta_path};
-use utils::files::{collect_files, collect_tables, filter_duplicates, get_paths_in_dir, read_file, write_file, delete_file, read_binary_file};
-use utils::vector::{self, find_vectors_in_dir, VectorInfo};
+use tauri::AppHandle;
+use utils::codewalkercli::{
+    cancel_command, get_cli_queue, get_cli_status, restart_codewalker, send_command, start_codewalker, stop_codewalker, validate_gta_path,
+};
+use utils::config_diff::{diff_lua_configs, merge_lua_configs};
+use utils::duplicates::{find_duplicates, find_stream_conflicts, resolve_duplicates, DuplicateGroup, DuplicateMode, StreamConflict};
+use utils::path_access::{check_path, get_allowed_roots, pick_allowed_root, remove_allowed_root};
+use utils::history::{backup_file, list_file_history, restore_file_version};
+use utils::files::{collect_tables, get_paths_in_dir, read_file, write_file, delete_file, open_folder, read_binary_file};
+use utils::lua_writer::set_lua_values;
+use utils::quarantine::{list_quarantines, restore_quarantine};
+use utils::resources::{ensure_order, ResourceResolver};
+use utils::scan::{get_scan_config, set_scan_config};
+use utils::vector::{self, CoordinateCluster, HeadingMode, SourceType, VectorFilter, VectorInfo, VectorMatch, VectorRegion};
+use utils::vector_export::export_vectors;
+use utils::vector_index::with_index;
+use utils::vector_transform::transform_vectors;
 
 #[tauri::command]
-fn find_vectors_in_distance(path: String, v: Vec<f32>, dist: f32) -> Vec<VectorInfo> {
+fn find_vectors_in_distance(
+    path: String,
+    v: Vec<f32>,
+    dist: f32,
+    file_types: Option<Vec<SourceType>>,
+    include_comments: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<VectorInfo>, String> {
     println!("Searching for vectors in: {:?}", path);
     if path.is_empty() {
-        return Vec::new();
+        return Ok(Vec::new());
     }
-    let search_path = Path::new(&path);
+    let search_path = check_path(&app_handle, &path)?;
 
-    find_vectors_in_dir(search_path)
+    let filter = VectorFilter::new(file_types, include_comments);
+
+    Ok(with_index(&app_handle, &search_path, |index| index.within_distance(&v, dist, &filter))
         .into_par_iter()
-        .filter_map(|vec_info| {
-            let d = vector::distance(&v, &vec_info.vector);
-            if d <= dist {
-                Some((d, vec_info))
-            } else {
-                None
+        .map(|(_, vec_info)| vec_info)
+        .collect())
+}
+
+#[tauri::command]
+fn find_nearest_vectors(
+    path: String,
+    v: Vec<f32>,
+    k: usize,
+    file_types: Option<Vec<SourceType>>,
+    include_comments: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<VectorMatch>, String> {
+    println!("Searching for {} nearest vectors in: {:?}", k, path);
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let search_path = check_path(&app_handle, &path)?;
+    let filter = VectorFilter::new(file_types, include_comments);
+
+    Ok(with_index(&app_handle, &search_path, |index| index.nearest(&v, k, &filter)))
+}
+
+#[tauri::command]
+fn find_vectors_in_region(
+    path: String,
+    region: VectorRegion,
+    heading: Option<HeadingMode>,
+    file_types: Option<Vec<SourceType>>,
+    include_comments: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<VectorInfo>, String> {
+    println!("Searching for vectors in {:?} in: {:?}", region, path);
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let search_path = check_path(&app_handle, &path)?;
+    let filter = VectorFilter::new(file_types, include_comments);
+    let heading = heading.unwrap_or_default();
+
+    Ok(with_index(&app_handle, &search_path, |index| index.within_region(&region, &heading, &filter)))
+}
+
+#[tauri::command]
+fn find_coordinate_clusters(
+    path: String,
+    tolerance: f32,
+    file_types: Option<Vec<SourceType>>,
+    include_comments: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<CoordinateCluster>, String> {
+    println!("Searching for coordinate clusters within {} in: {:?}", tolerance, path);
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let search_path = check_path(&app_handle, &path)?;
+    let filter = VectorFilter::new(file_types, include_comments);
+
+    let clusters = with_index(&app_handle, &search_path, |index| index.clusters(tolerance, &filter));
+    let mut resolver = ResourceResolver::new(&search_path);
+
+    Ok(clusters
+        .into_iter()
+        .map(|members| {
+            let mut center = vec![0.0; 3];
+            for member in &members {
+                for (c, v) in center.iter_mut().zip(vector::position(&member.vector)) {
+                    *c += v / members.len() as f32;
+                }
+            }
+
+            let mut files: Vec<String> = members.iter().map(|member| member.file.clone()).collect();
+            files.dedup();
+            let mut resources: Vec<String> = files.iter().filter_map(|file| resolver.name_of(file)).collect();
+            resources.sort();
+            resources.dedup();
+
+            CoordinateCluster {
+                center,
+                files,
+                resources,
+                members,
             }
         })
-        .collect::<Vec<_>>()
-        .into_par_iter()
-        .map(|(_, vec_info)| vec_info)
-        .collect()
+        .collect())
+}
+
+#[tauri::command]
+fn find_duplicate_files(
+    path: String,
+    filter: Vec<String>,
+    mode: Option<DuplicateMode>,
+    app_handle: AppHandle,
+) -> Result<Vec<DuplicateGroup>, String> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(find_duplicates(&check_path(&app_handle, &path)?, &filter, mode.unwrap_or_default()))
 }
 
 #[tauri::command]
-fn find_duplicate_files(path: String, filter: Vec<String>) -> Vec<(String, Vec<String>)> {
+fn find_streaming_conflicts(
+    path: String,
+    server_cfg: Option<String>,
+    app_handle: AppHandle,
+) -> Result<Vec<StreamConflict>, String> {
+    println!("Searching for streaming conflicts in: {:?}", path);
     if path.is_empty() {
-        return Vec::new();
+        return Ok(Vec::new());
     }
-    filter_duplicates(collect_files(Path::new(&path), &filter))
+    let search_path = check_path(&app_handle, &path)?;
+
+    let order = match server_cfg.filter(|cfg| !cfg.is_empty()) {
+        Some(cfg) => {
+            let cfg_path = check_path(&app_handle, &cfg)?;
+            ensure_order(&std::fs::read_to_string(cfg_path).map_err(|e| format!("{}: {}", cfg, e))?)
+        }
+        None => Vec::new(),
+    };
+    Ok(find_stream_conflicts(&search_path, &order))
 }
 
 #[tauri::command]
-fn get_lua_tables(path: String, table_filter: Vec<String>) -> Vec<String> {
+fn get_lua_tables(
+    path: String,
+    table_filter: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
     if path.is_empty() {
-        return Vec::new();
+        return Ok(serde_json::Map::new());
     }
-    collect_tables(Path::new(&path), table_filter).unwrap_or_default()
+    collect_tables(&check_path(&app_handle, &path)?, table_filter).map_err(|e| e.to_string())
 }
 
 
@@ -64,15 +205,40 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .invoke_handler(tauri::generate_handler![
             find_vectors_in_distance,
+            find_nearest_vectors,
+            find_vectors_in_region,
+            transform_vectors,
+            find_coordinate_clusters,
+            export_vectors,
             find_duplicate_files,
+            find_streaming_conflicts,
+            resolve_duplicates,
+            list_quarantines,
+            restore_quarantine,
+            get_scan_config,
+            set_scan_config,
             get_lua_tables,
+            set_lua_values,
+            diff_lua_configs,
+            merge_lua_configs,
             send_command,
+            get_cli_queue,
+            get_cli_status,
+            cancel_command,
+            restart_codewalker,
             stop_codewalker,
             start_codewalker,
             get_paths_in_dir,
             read_file,
             write_file,
             delete_file,
+            open_folder,
+            get_allowed_roots,
+            pick_allowed_root,
+            remove_allowed_root,
+            backup_file,
+            list_file_history,
+            restore_file_version,
             validate_gta_path,
             read_binary_file,
         ])
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON-lines protocol spoken by CodeWalkerCli. Messages with any other
+/// version are rejected rather than guessed at.
+pub const PROTOCOL_VERSION: u64 = 1;
+/// Environment variable asking the CLI for JSON lines. Builds that don't know it ignore it and
+/// keep printing the old `[CMD]`/`[OUTPUT_START]` markers.
+pub const PROTOCOL_ENV: &str = "CLI_PROTOCOL";
+pub const PROTOCOL_NAME: &str = "jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultStatus {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CliProgress {
+    pub id: u64,
+    pub done: u32,
+    pub total: u32,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// One line written by the CLI.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CliMessage {
+    /// The cache is loaded and commands can be sent.
+    Ready,
+    /// The CLI has read request `id` and is running it.
+    Started { id: u64 },
+    Progress(CliProgress),
+    /// Request `id` finished, with its output on success or the reason on failure.
+    Result {
+        id: u64,
+        status: ResultStatus,
+        #[serde(default)]
+        payload: Option<String>,
+        #[serde(default)]
+        error: Option<String>,
+    },
+    /// A log line, tied to the request that was running when it was written.
+    Log {
+        #[serde(default)]
+        id: Option<u64>,
+        level: String,
+        message: String,
+    },
+}
+
+/// A command as sent to the CLI.
+#[derive(Debug, Serialize)]
+pub struct CliRequestLine<'a> {
+    pub v: u64,
+    pub id: u64,
+    pub command: &'a str,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("Unsupported protocol version {0}, expected {PROTOCOL_VERSION}")]
+    UnsupportedVersion(u64),
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliLine {
+    Message(CliMessage),
+    /// Anything that isn't a JSON object, such as help output or a marker line.
+    Text(String),
+}
+
+/// Parses one line of CLI output.
+pub fn parse_line(line: &str) -> Result<CliLine, ProtocolError> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return Ok(CliLine::Text(line.to_string()));
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| ProtocolError::InvalidMessage(e.to_string()))?;
+    let version = value
+        .get("v")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or_else(|| ProtocolError::InvalidMessage("missing version".to_string()))?;
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::UnsupportedVersion(version));
+    }
+
+    serde_json::from_value(value)
+        .map(CliLine::Message)
+        .map_err(|e| ProtocolError::InvalidMessage(e.to_string()))
+}
+
+/// Serializes a request for the CLI, without the trailing newline.
+pub fn request_line(id: u64, command: &str) -> String {
+    // A struct of a number and a string always serializes.
+    serde_json::to_string(&CliRequestLine { v: PROTOCOL_VERSION, id, command }).unwrap_or_default()
+}
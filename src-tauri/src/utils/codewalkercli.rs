@@ -1,19 +1,26 @@
 use once_cell::sync::Lazy;
 use serde::Serialize;
 use std::{
+    collections::VecDeque,
     path::Path,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
 };
 use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot};
 use tauri_plugin_shell::{
     process::{CommandChild, CommandEvent},
     ShellExt,
 };
 
+use super::cli_protocol::{self, CliLine, CliMessage, ResultStatus, PROTOCOL_ENV, PROTOCOL_NAME};
+
 #[derive(Debug, Clone)]
 struct CommandResult {
     output: Vec<String>,
-    complete: bool,
     start_index: Option<usize>,
     end_index: Option<usize>,
 }
@@ -22,24 +29,89 @@ impl CommandResult {
     fn new() -> Self {
         Self {
             output: Vec::new(),
-            complete: false,
             start_index: None,
             end_index: None,
         }
     }
+
+    /// The lines between the output markers, or everything if there were none, without the
+    /// CLI's own log lines.
+    fn filtered_output(&self) -> String {
+        let output = match (self.start_index, self.end_index) {
+            (Some(start), Some(end)) if start < end => &self.output[start..end - 1],
+            _ => &self.output[..],
+        };
+
+        output
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('[') && !line.starts_with("Checking"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A command waiting for its turn; the caller awaits `reply`.
+struct CliRequest {
+    id: u64,
+    command: String,
+    reply: oneshot::Sender<Result<String, CliError>>,
+}
+
+/// The request the CLI is working on and the output it has produced so far.
+struct ActiveRequest {
+    request: CliRequest,
+    result: CommandResult,
+    dispatched: Instant,
+    /// Set once the CLI has read the command. Some commands report completion twice, and the
+    /// late one must not complete the request sent after them.
+    started: bool,
+}
+
+/// Lifecycle of the CLI process, sent to the frontend as `cli-status` events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum CliStatus {
+    Starting,
+    Ready,
+    /// The process exited without being asked to.
+    Crashed { code: Option<i32> },
+    Restarting { attempt: u32, delay_ms: u64 },
+    Stopped,
+}
+
+/// How the running CLI frames its output, decided by how it reports being ready. Older builds
+/// only know the marker lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Unknown,
+    JsonLines,
+    Markers,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedCommand {
+    pub id: u64,
+    pub command: String,
+    pub running: bool,
 }
 
 const DEFAULT_GTA_PATH: &str = "C:/Program Files/Rockstar Games/Grand Theft Auto V";
 const CACHE_INITIALIZED_MSG: &str = "[INFO] Cache initialized";
-const PROCESSING_COMMAND_MSG: &str = "[CMD]";
+const PROCESSING_COMMAND_MSG: &str = "[CMD] Processing command";
 const COMMAND_COMPLETE_MSG: &str = "[CMD] Command completed";
 const OUTPUT_START_MSG: &str = "[OUTPUT_START]";
 const OUTPUT_END_MSG: &str = "[OUTPUT_END]";
 const CLI_ERROR: &str = "[TAURI ERROR]";
 const CLI_INFO: &str = "[TAURI INFO]";
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+/// Delay before the first automatic restart after a crash, doubled for each further attempt.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+const MAX_RESTARTS: u32 = 5;
 
-static CURRENT_COMMAND: Lazy<Arc<Mutex<CommandResult>>> = Lazy::new(|| Arc::new(Mutex::new(CommandResult::new())));
-    
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
 
 #[derive(Debug, thiserror::Error)]
 pub enum CliError {
@@ -53,10 +125,12 @@ pub enum CliError {
     ExecutableNotFound(String),
     #[error("CLI is not running")]
     NotRunning,
-    #[error("CLI is not ready")]
-    NotReady,
-    #[error("CLI is busy processing")]
-    Busy,
+    #[error("Command timed out after {0}s")]
+    Timeout(u64),
+    #[error("Command was cancelled")]
+    Cancelled,
+    #[error("CLI exited unexpectedly with code {0}")]
+    Terminated(i32),
     #[error("Lock acquisition failed: {0}")]
     LockError(String),
     #[error("Command failed: {0}")]
@@ -74,105 +148,397 @@ impl Serialize for CliError {
     }
 }
 
+/// What a running CLI reports back, line by line.
+#[derive(Debug, Clone)]
+pub enum CliEvent {
+    Stdout(String),
+    Stderr(String),
+    Error(String),
+    Terminated(Option<i32>),
+}
+
+/// A spawned CLI process that commands are written to.
+pub trait CliChild: Send {
+    fn write(&mut self, data: &[u8]) -> Result<(), CliError>;
+    fn kill(self: Box<Self>) -> Result<(), CliError>;
+}
+
+/// Starts CLI processes. The app launches the bundled CodeWalkerCli; tests plug in a scripted
+/// stand-in instead.
+pub trait CliLauncher: Send + Sync {
+    fn launch(&self, gta_path: &str) -> Result<(Box<dyn CliChild>, mpsc::UnboundedReceiver<CliEvent>), CliError>;
+}
+
+/// Receives the output, status and queue updates meant for the frontend.
+pub trait CliEvents: Send + Sync {
+    fn emit(&self, event: &str, payload: serde_json::Value);
+}
+
+impl CliEvents for AppHandle {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        if let Err(e) = Emitter::emit(self, event, payload) {
+            println!("{} Error emitting event: {}", CLI_ERROR, e);
+        }
+    }
+}
+
+/// Runs `CodeWalkerCli` through the shell plugin.
+struct ShellLauncher {
+    app_handle: AppHandle,
+}
+
+struct ShellChild(CommandChild);
+
+impl CliChild for ShellChild {
+    fn write(&mut self, data: &[u8]) -> Result<(), CliError> {
+        self.0.write(data).map_err(|e| CliError::CommandError(e.to_string()))
+    }
+
+    fn kill(self: Box<Self>) -> Result<(), CliError> {
+        self.0.kill().map_err(|e| CliError::CommandError(e.to_string()))
+    }
+}
+
+impl CliLauncher for ShellLauncher {
+    fn launch(&self, gta_path: &str) -> Result<(Box<dyn CliChild>, mpsc::UnboundedReceiver<CliEvent>), CliError> {
+        let exe_path = Path::new(gta_path).join("GTA5.exe");
+        if !exe_path.exists() {
+            return Err(CliError::ExecutableNotFound(gta_path.to_string()));
+        }
+
+        let (mut rx, child) = self
+            .app_handle
+            .shell()
+            .command("CodeWalkerCli")
+            .env("CLIMODE", "PIPED")
+            .env("GTA_PATH", gta_path)
+            .env(PROTOCOL_ENV, PROTOCOL_NAME)
+            .spawn()
+            .map_err(|e| CliError::CommandError(e.to_string()))?;
+
+        let (tx, events) = mpsc::unbounded_channel();
+        tauri::async_runtime::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let event = match event {
+                    CommandEvent::Stdout(line) => CliEvent::Stdout(String::from_utf8_lossy(&line).to_string()),
+                    CommandEvent::Stderr(line) => CliEvent::Stderr(String::from_utf8_lossy(&line).to_string()),
+                    CommandEvent::Error(error) => CliEvent::Error(error.to_string()),
+                    CommandEvent::Terminated(status) => CliEvent::Terminated(status.code),
+                    _ => continue,
+                };
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok((Box::new(ShellChild(child)), events))
+    }
+}
+
+/// The running CLI and its command queue. The CLI handles one command at a time, so requests
+/// wait in `queue` until the active one completes.
 struct CliProcess {
-    command: Option<CommandChild>,
+    command: Option<Box<dyn CliChild>>,
+    gta_path: String,
+    launcher: Arc<dyn CliLauncher>,
+    events: Arc<dyn CliEvents>,
+    /// Identifies the spawned process, so output from one that was killed is ignored.
+    generation: u64,
+    /// When the current process was spawned, so requests can't wait forever for it to load.
+    spawned: Instant,
     ready: bool,
-    processing_command: bool,
+    mode: OutputMode,
+    status: CliStatus,
+    /// Restart after a crash, up to `MAX_RESTARTS` times in a row.
+    auto_restart: bool,
+    restarts: u32,
+    queue: VecDeque<CliRequest>,
+    active: Option<ActiveRequest>,
 }
 
 impl CliProcess {
-    fn new(command: CommandChild) -> Self {
-        Self {
-            command: Some(command),
-            ready: false,
-            processing_command: false,
+    fn set_ready(&mut self) {
+        self.ready = true;
+        self.restarts = 0;
+        self.set_status(CliStatus::Ready);
+        self.dispatch_next();
+    }
+
+    fn set_status(&mut self, status: CliStatus) {
+        println!("{} Status: {:?}", CLI_INFO, status);
+        self.emit("cli-status", &status);
+        self.status = status;
+    }
+
+    fn emit(&self, event: &str, payload: impl Serialize) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => self.events.emit(event, payload),
+            Err(e) => println!("{} Error serializing event: {}", CLI_ERROR, e),
         }
     }
 
-    fn set_ready(&mut self) {
-        self.ready = true;
+    fn emit_output(&self, line: String) {
+        print!("{} {}", CLI_INFO, line);
+        self.emit("cli-output", line);
     }
 
-    fn set_processing(&mut self, processing: bool) {
-        self.processing_command = processing;
+    /// Handles the process exiting on its own: every pending request fails, and a restart is
+    /// scheduled if enabled. Returns false when the process should be dropped instead.
+    fn handle_exit(&mut self, code: Option<i32>) -> bool {
+        self.command = None;
+        self.ready = false;
+        let pending = self.active.take().map(|active| active.request).into_iter().chain(self.queue.drain(..));
+        for request in pending.collect::<Vec<_>>() {
+            let _ = request.reply.send(Err(CliError::Terminated(code.unwrap_or(-1))));
+        }
+        self.emit_queue();
+        self.set_status(CliStatus::Crashed { code });
+
+        if !self.auto_restart || self.restarts >= MAX_RESTARTS {
+            return false;
+        }
+        self.schedule_restart();
+        true
+    }
+
+    fn schedule_restart(&mut self) {
+        let delay = RESTART_DELAY
+            .saturating_mul(2u32.saturating_pow(self.restarts))
+            .min(MAX_RESTART_DELAY);
+        self.restarts += 1;
+        self.set_status(CliStatus::Restarting {
+            attempt: self.restarts,
+            delay_ms: delay.as_millis() as u64,
+        });
+
+        let generation = self.generation;
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let Ok(mut guard) = lock_cli() else {
+                return;
+            };
+            // Skip if the CLI was stopped or started again in the meantime.
+            let Some(cli) = guard
+                .as_mut()
+                .filter(|cli| cli.generation == generation && cli.command.is_none())
+            else {
+                return;
+            };
+            if let Err(e) = cli.spawn() {
+                println!("{} Error restarting CLI: {}", CLI_ERROR, e);
+                if !cli.handle_exit(None) {
+                    *guard = None;
+                }
+            }
+        });
     }
 
     fn is_ready(&self) -> bool {
         self.ready
     }
 
-    async fn start(gta_path: String, app_handle: &AppHandle) -> Result<Self, CliError> {
-        let gta_path = validate_gta_path(gta_path)?;
+    fn enqueue(&mut self, request: CliRequest) {
+        self.queue.push_back(request);
+        self.dispatch_next();
+    }
 
-        let exe_path = Path::new(&gta_path).join("GTA5.exe");
-        if !exe_path.exists() {
-            return Err(CliError::ExecutableNotFound(gta_path));
+    /// Sends the next queued command once the CLI is ready and idle. Requests whose caller has
+    /// stopped waiting are dropped without being sent.
+    fn dispatch_next(&mut self) {
+        while self.ready && self.active.is_none() {
+            let Some(request) = self.queue.pop_front() else {
+                break;
+            };
+            if request.reply.is_closed() {
+                continue;
+            }
+            println!("{} Executing #{}: {}", CLI_INFO, request.id, request.command);
+            let line = match self.mode {
+                OutputMode::JsonLines => cli_protocol::request_line(request.id, &request.command),
+                _ => request.command.clone(),
+            };
+            match self.write_line(&line) {
+                Ok(()) => {
+                    self.active = Some(ActiveRequest {
+                        request,
+                        result: CommandResult::new(),
+                        dispatched: Instant::now(),
+                        started: false,
+                    });
+                }
+                Err(e) => {
+                    let _ = request.reply.send(Err(e));
+                }
+            }
         }
+        self.emit_queue();
+    }
 
-        let shell = app_handle.shell();
-        let (mut rx, command) = shell
-            .command("CodeWalkerCli")
-            .env("CLIMODE", "PIPED")
-            .env("GTA_PATH", gta_path)
-            .spawn()
-            .map_err(|e| CliError::CommandError(e.to_string()))?;
+    fn emit_queue(&self) {
+        self.emit("cli-queue", self.queued());
+    }
 
-        let process = Self::new(command);
+    /// Fails request `id` with `error`. A running command can't be interrupted, so the CLI is
+    /// restarted to stop it. Returns false if the request isn't queued or running.
+    fn fail(&mut self, id: u64, error: CliError) -> bool {
+        if let Some(index) = self.queue.iter().position(|request| request.id == id) {
+            if let Some(request) = self.queue.remove(index) {
+                let _ = request.reply.send(Err(error));
+            }
+            self.emit_queue();
+            return true;
+        }
 
-        let app_handle = app_handle.clone();
+        let Some(active) = self.active.take_if(|active| active.request.id == id) else {
+            return false;
+        };
+        println!("{} Stopping #{}: {}", CLI_INFO, id, error);
+        let _ = active.request.reply.send(Err(error));
+        if let Err(e) = self.restart() {
+            println!("{} Error restarting CLI: {}", CLI_ERROR, e);
+        }
+        true
+    }
+
+    /// How much longer request `id`, queued at `queued`, may wait. Time spent behind other
+    /// commands doesn't count, but time spent waiting for the CLI to become ready does.
+    fn remaining(&self, id: u64, queued: Instant, timeout: Duration) -> Duration {
+        match &self.active {
+            Some(active) if active.request.id == id => timeout.saturating_sub(active.dispatched.elapsed()),
+            _ if !self.ready => timeout.saturating_sub(queued.max(self.spawned).elapsed()),
+            _ => timeout,
+        }
+    }
+
+    fn is_running(&self, id: u64) -> bool {
+        self.active.as_ref().is_some_and(|active| active.request.id == id)
+    }
+
+    /// Hands the active request its result and moves on to the next one.
+    fn complete_active(&mut self, result: Result<String, CliError>) {
+        if let Some(active) = self.active.take() {
+            let _ = active.request.reply.send(result);
+        }
+        self.dispatch_next();
+    }
+
+    fn queued(&self) -> Vec<QueuedCommand> {
+        let active = self.active.iter().map(|active| QueuedCommand {
+            id: active.request.id,
+            command: active.request.command.clone(),
+            running: true,
+        });
+        let waiting = self.queue.iter().map(|request| QueuedCommand {
+            id: request.id,
+            command: request.command.clone(),
+            running: false,
+        });
+        active.chain(waiting).collect()
+    }
+
+    fn start(
+        gta_path: String,
+        auto_restart: bool,
+        launcher: Arc<dyn CliLauncher>,
+        events: Arc<dyn CliEvents>,
+    ) -> Result<Self, CliError> {
+        let mut process = Self {
+            command: None,
+            gta_path,
+            launcher,
+            events,
+            generation: 0,
+            spawned: Instant::now(),
+            ready: false,
+            mode: OutputMode::Unknown,
+            status: CliStatus::Starting,
+            auto_restart,
+            restarts: 0,
+            queue: VecDeque::new(),
+            active: None,
+        };
+        process.spawn()?;
+        Ok(process)
+    }
+
+    /// Spawns the CLI with a task that feeds its output back into `CLI_PROCESS`.
+    fn spawn(&mut self) -> Result<(), CliError> {
+        let (command, mut rx) = self.launcher.launch(&self.gta_path)?;
+
+        let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        self.command = Some(command);
+        self.generation = generation;
+        self.spawned = Instant::now();
+        self.ready = false;
+        self.mode = OutputMode::Unknown;
+        self.set_status(CliStatus::Starting);
+
+        let events = Arc::clone(&self.events);
         let cli_clone = Arc::clone(&CLI_PROCESS);
         tauri::async_runtime::spawn(async move {
             while let Some(event) = rx.recv().await {
                 match event {
-                    CommandEvent::Stdout(line) => {
-                        let line_str = String::from_utf8_lossy(&line);
+                    CliEvent::Stdout(line) => {
                         if let Ok(mut guard) = cli_clone.lock() {
-                            if let Some(cli) = guard.as_mut() {
-                                if let Err(e) = handle_stdout(line_str.to_string(), &app_handle, cli) {
+                            if let Some(cli) = guard.as_mut().filter(|cli| cli.generation == generation) {
+                                if let Err(e) = handle_stdout(line, cli) {
                                     println!("{} Error handling stdout: {}", CLI_ERROR, e);
                                 }
                             }
                         }
                     }
-                    CommandEvent::Stderr(line) => {
-                        let line_str = String::from_utf8_lossy(&line);
-                        println!("{} stderr: {}", CLI_ERROR, line_str);
-                        let _ = app_handle.emit("cli-error", line_str.to_string());
+                    CliEvent::Stderr(line) => {
+                        println!("{} stderr: {}", CLI_ERROR, line);
+                        events.emit("cli-error", line.into());
                     }
-                    CommandEvent::Error(error) => {
+                    CliEvent::Error(error) => {
                         println!("{} Process error: {}", CLI_ERROR, error);
-                        let _ = app_handle.emit("cli-error", error.to_string());
+                        events.emit("cli-error", error.into());
                     }
-                    CommandEvent::Terminated(status) => {
-                        println!(
-                            "{} Process terminated ({})",
-                            CLI_INFO,
-                            status.code.unwrap_or(-1)
-                        );
+                    CliEvent::Terminated(code) => {
+                        println!("{} Process terminated ({})", CLI_INFO, code.unwrap_or(-1));
+                        // A process that was stopped or restarted on purpose has been replaced already.
+                        if let Ok(mut guard) = cli_clone.lock() {
+                            let exited = guard.as_mut().filter(|cli| cli.generation == generation);
+                            if exited.is_some_and(|cli| !cli.handle_exit(code)) {
+                                *guard = None;
+                            }
+                        }
                         break;
                     }
-                    _ => {}
                 }
             }
         });
-        
-        Ok(process)
+
+        Ok(())
     }
 
-    fn send_command(&mut self, command: String) -> Result<(), CliError> {
-        if !self.ready {
-            return Err(CliError::NotReady);
+    /// Kills the CLI and starts a fresh one. Queued commands are kept and run once it is ready;
+    /// if it can't be started they fail instead of waiting forever.
+    fn restart(&mut self) -> Result<(), CliError> {
+        println!("{} Restarting CLI", CLI_INFO);
+        if let Some(cmd) = self.command.take() {
+            let _ = cmd.kill();
+        }
+        if let Some(active) = self.active.take() {
+            let _ = active.request.reply.send(Err(CliError::Cancelled));
         }
-        if self.processing_command {
-            return Err(CliError::Busy);
+
+        if let Err(e) = self.spawn() {
+            for request in self.queue.drain(..) {
+                let _ = request.reply.send(Err(CliError::CommandError(e.to_string())));
+            }
+            self.emit_queue();
+            return Err(e);
         }
+        self.emit_queue();
+        Ok(())
+    }
 
+    fn write_line(&mut self, command: &str) -> Result<(), CliError> {
         if let Some(cmd) = &mut self.command {
             cmd.write(format!("{}\n", command).as_bytes())
-                .map_err(|e| CliError::CommandError(e.to_string()))?;
-
-            self.set_processing(true);
-            Ok(())
         } else {
             Err(CliError::NotRunning)
         }
@@ -181,8 +547,6 @@ impl CliProcess {
     fn stop(&mut self) -> Result<(), CliError> {
         if let Some(cmd) = self.command.take() {
             cmd.kill()
-                .map_err(|e| CliError::CommandError(e.to_string()))?;
-            Ok(())
         } else {
             Err(CliError::NotRunning)
         }
@@ -212,129 +576,208 @@ pub fn validate_gta_path(path: String) -> Result<String, CliError> {
 }
 
 static CLI_PROCESS: Lazy<Arc<Mutex<Option<CliProcess>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
-    fn handle_stdout(line_str: String, app_handle: &AppHandle, cli: &mut CliProcess) -> Result<(), CliError> {
-        let mut cmd_result = CURRENT_COMMAND
-            .lock()
-            .map_err(|e| CliError::LockError(e.to_string()))?;
-        
-        cmd_result.output.push(line_str.clone());
-        
-        match line_str {
-            _ if line_str.contains(OUTPUT_START_MSG) => {
-                println!("{} Output Start", CLI_INFO);
-                cmd_result.start_index = Some(cmd_result.output.len());
+
+fn lock_cli() -> Result<MutexGuard<'static, Option<CliProcess>>, CliError> {
+    CLI_PROCESS
+        .lock()
+        .map_err(|e| CliError::LockError(format!("{} {}", CLI_ERROR, e)))
+}
+/// Routes a line of CLI output. Until the CLI has said which protocol it speaks, lines are
+/// tried as JSON first and fall back to the old markers.
+fn handle_stdout(line_str: String, cli: &mut CliProcess) -> Result<(), CliError> {
+    if cli.mode != OutputMode::Markers {
+        match cli_protocol::parse_line(&line_str) {
+            Ok(CliLine::Message(message)) => {
+                cli.mode = OutputMode::JsonLines;
+                return handle_message(message, cli);
             }
-            _ if line_str.contains(OUTPUT_END_MSG) => {
-                println!("{} Output End", CLI_INFO);
-                cmd_result.end_index = Some(cmd_result.output.len());
+            Ok(CliLine::Text(text)) if cli.mode == OutputMode::JsonLines => {
+                cli.emit_output(format!("{}\n", text));
+                return Ok(());
             }
-            _ if line_str.contains(COMMAND_COMPLETE_MSG) => {
-                cli.set_processing(false);
-                cmd_result.complete = true;
+            Err(e) if cli.mode == OutputMode::JsonLines => {
+                println!("{} {}: {}", CLI_ERROR, e, line_str.trim());
+                return Ok(());
             }
-            _ if !cli.is_ready() && line_str.contains(CACHE_INITIALIZED_MSG) => {
+            _ => {}
+        }
+    }
+
+    handle_marker_line(line_str, cli)
+}
+
+fn handle_message(message: CliMessage, cli: &mut CliProcess) -> Result<(), CliError> {
+    match message {
+        CliMessage::Ready => {
+            if !cli.is_ready() {
                 println!("{} CLI Ready", CLI_INFO);
                 cli.set_ready();
             }
-            _ if line_str.contains(PROCESSING_COMMAND_MSG) => {
-                cli.set_processing(true);
+        }
+        CliMessage::Started { id } => {
+            if let Some(active) = cli.active.as_mut().filter(|active| active.request.id == id) {
+                active.started = true;
             }
-            _ => {}
         }
-    
-        if !cli.processing_command {
-            print!("{} {}", CLI_INFO, line_str);
-            if let Err(e) = app_handle.emit("cli-output", line_str) {
-                println!("{} Error emitting event: {}", CLI_ERROR, e);
+        CliMessage::Progress(progress) => {
+            cli.emit("cli-progress", progress);
+        }
+        CliMessage::Result { id, status, payload, error } => {
+            // A result for a request that already timed out or was cancelled is dropped.
+            if !cli.is_running(id) {
+                println!("{} Ignoring result for #{}", CLI_INFO, id);
+                return Ok(());
             }
+            let result = match status {
+                ResultStatus::Ok => Ok(payload.unwrap_or_default()),
+                ResultStatus::Error => Err(CliError::CommandError(error.unwrap_or_default())),
+            };
+            cli.complete_active(result);
+        }
+        CliMessage::Log { level, message, .. } => {
+            cli.emit_output(format!("[{}] {}\n", level.to_uppercase(), message));
         }
-    
-        Ok(())
     }
+    Ok(())
+}
 
-#[tauri::command]
-pub async fn start_codewalker(gta_path: String, app_handle: AppHandle) -> Result<(), CliError> {
-    println!("{} Checking if process is already running...", CLI_INFO);
-    {
-        let guard = CLI_PROCESS
-            .lock()
-            .map_err(|e| CliError::LockError(e.to_string()))?;
+fn handle_marker_line(line_str: String, cli: &mut CliProcess) -> Result<(), CliError> {
+    if let Some(active) = cli.active.as_mut() {
+        let result = &mut active.result;
+        result.output.push(line_str.clone());
 
-        if guard.is_some() {
-            println!("{} Process already running", CLI_ERROR);
-            return Err(CliError::AlreadyRunning);
+        if line_str.contains(OUTPUT_START_MSG) {
+            println!("{} Output Start", CLI_INFO);
+            result.start_index = Some(result.output.len());
+        } else if line_str.contains(OUTPUT_END_MSG) {
+            println!("{} Output End", CLI_INFO);
+            result.end_index = Some(result.output.len());
+        } else if line_str.contains(PROCESSING_COMMAND_MSG) {
+            active.started = true;
+        } else if active.started && line_str.contains(COMMAND_COMPLETE_MSG) {
+            println!(
+                "{} Output finished {}:{}",
+                CLI_INFO,
+                result.start_index.unwrap_or(0),
+                result.end_index.unwrap_or(0)
+            );
+            let output = result.filtered_output();
+            cli.complete_active(Ok(output));
         }
+    } else if !cli.is_ready() && line_str.contains(CACHE_INITIALIZED_MSG) {
+        println!("{} CLI Ready", CLI_INFO);
+        cli.mode = OutputMode::Markers;
+        cli.set_ready();
     }
 
-    println!("{} Starting process...", CLI_INFO);
-    let process = CliProcess::start(gta_path, &app_handle).await?;
-
-    let mut guard = CLI_PROCESS
-        .lock()
-        .map_err(|e| CliError::LockError(e.to_string()))?;
+    if cli.active.is_none() {
+        cli.emit_output(line_str);
+    }
 
-    *guard = Some(process);
-    println!("{} Process started", CLI_INFO);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn send_command(command: String) -> Result<String, CliError> {
-    println!("{} Executing: {}", CLI_INFO, command);
+pub async fn start_codewalker(
+    gta_path: String,
+    auto_restart: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<(), CliError> {
+    let gta_path = validate_gta_path(gta_path)?;
+    install_process(
+        gta_path,
+        auto_restart.unwrap_or(false),
+        Arc::new(ShellLauncher { app_handle: app_handle.clone() }),
+        Arc::new(app_handle),
+    )
+}
 
-    {
-        let mut cmd_result = CURRENT_COMMAND.lock().map_err(|e| CliError::LockError(format!("{} {}", CLI_ERROR, e)))?;
-        *cmd_result = CommandResult::new();
+/// Starts the CLI and stores it in `CLI_PROCESS`. The lock is held until it is stored, so the
+/// reader task can't miss output that arrives straight away.
+fn install_process(
+    gta_path: String,
+    auto_restart: bool,
+    launcher: Arc<dyn CliLauncher>,
+    events: Arc<dyn CliEvents>,
+) -> Result<(), CliError> {
+    println!("{} Checking if process is already running...", CLI_INFO);
+    let mut guard = lock_cli()?;
+    if guard.is_some() {
+        println!("{} Process already running", CLI_ERROR);
+        return Err(CliError::AlreadyRunning);
     }
 
-    {
-        let cli_process = Arc::clone(&CLI_PROCESS);
-        let mut guard = cli_process
-            .lock()
-            .map_err(|e| CliError::LockError(format!("{} {}", CLI_ERROR, e)))?;
+    println!("{} Starting process...", CLI_INFO);
+    *guard = Some(CliProcess::start(gta_path, auto_restart, launcher, events)?);
+    println!("{} Process started", CLI_INFO);
+    Ok(())
+}
 
-        let cli = guard.as_mut().ok_or(CliError::NotRunning)?;
-        cli.send_command(command)?;
-    }
+/// Queues `command` behind any others and waits for its output. Commands sent before the
+/// CLI has finished loading wait until it is ready. A command that runs longer than
+/// `timeout_secs` fails with `CliError::Timeout` and the CLI is restarted; one still waiting
+/// for the CLI to become ready after `timeout_secs` fails the same way.
+#[tauri::command]
+pub async fn send_command(command: String, timeout_secs: Option<u64>) -> Result<String, CliError> {
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    println!("{} Queueing #{}: {}", CLI_INFO, id, command);
+
+    let (reply, mut response) = oneshot::channel();
+    let queued = Instant::now();
+    lock_cli()?
+        .as_mut()
+        .ok_or(CliError::NotRunning)?
+        .enqueue(CliRequest { id, command, reply });
 
     loop {
-        let output_to_process = {
-            let cmd_result = CURRENT_COMMAND.lock().unwrap();
-            if cmd_result.complete {
-                let output = match (cmd_result.start_index, cmd_result.end_index) {
-                    (Some(start), Some(end)) if start < end => {
-                        cmd_result.output[start+1..end-1].to_vec()
-                    },
-                    _ => cmd_result.output.clone()
-                };
-                
-                println!("{} Output finished {}:{}", CLI_INFO, 
-                    cmd_result.start_index.unwrap_or(0), 
-                    cmd_result.end_index.unwrap_or(0));
-                
-                Some(output)
-            } else {
-                None
+        let wait = lock_cli()?.as_ref().map_or(timeout, |cli| cli.remaining(id, queued, timeout));
+        match tokio::time::timeout(wait, &mut response).await {
+            Ok(reply) => {
+                // The sender is dropped without a reply when the CLI is stopped.
+                let output = reply.map_err(|_| CliError::NotRunning)??;
+                println!("{} Command #{} complete", CLI_INFO, id);
+                return Ok(output);
             }
-        };
+            Err(_) => {
+                let mut guard = lock_cli()?;
+                if let Some(cli) = guard.as_mut() {
+                    if cli.remaining(id, queued, timeout).is_zero() {
+                        cli.fail(id, CliError::Timeout(timeout.as_secs()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Cancels a queued or running command; its caller gets `CliError::Cancelled`. Returns false
+/// if the command already finished.
+#[tauri::command]
+pub fn cancel_command(request_id: u64) -> Result<bool, CliError> {
+    println!("{} Cancelling #{}", CLI_INFO, request_id);
+    Ok(lock_cli()?
+        .as_mut()
+        .is_some_and(|cli| cli.fail(request_id, CliError::Cancelled)))
+}
 
-        if let Some(output) = output_to_process {
-            let filtered_output = output.iter()
-                .map(|line| line.trim())
-                .filter(|line| {
-                    !line.is_empty() && 
-                    !line.starts_with('[') && 
-                    !line.starts_with("Checking")
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
+/// Restarts an unresponsive CLI. The running command is cancelled; queued ones are kept.
+#[tauri::command]
+pub fn restart_codewalker() -> Result<(), CliError> {
+    lock_cli()?.as_mut().ok_or(CliError::NotRunning)?.restart()
+}
 
-            println!("{} Command complete", CLI_INFO);
-            return Ok(filtered_output);
-        }
+#[tauri::command]
+pub fn get_cli_status() -> Result<CliStatus, CliError> {
+    Ok(lock_cli()?
+        .as_ref()
+        .map_or(CliStatus::Stopped, |cli| cli.status.clone()))
+}
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
-    }
+/// The running command followed by the ones waiting behind it.
+#[tauri::command]
+pub fn get_cli_queue() -> Result<Vec<QueuedCommand>, CliError> {
+    Ok(lock_cli()?.as_ref().map(CliProcess::queued).unwrap_or_default())
 }
 
 #[tauri::command]
@@ -348,6 +791,7 @@ pub async fn stop_codewalker() -> Result<(), CliError> {
 
     match guard.take() {
         Some(mut cli) => {
+            cli.set_status(CliStatus::Stopped);
             cli.stop()?;
             println!("{} Process stopped", CLI_INFO);
             Ok(())
@@ -358,3 +802,344 @@ pub async fn stop_codewalker() -> Result<(), CliError> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Tests share `CLI_PROCESS`, so they take turns.
+    static SERIAL: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+    const READY: &str = "[INFO] Cache initialized";
+    const JSON_READY: &str = r#"{"v":1,"type":"ready"}"#;
+
+    /// What the fake CLI does, in order.
+    #[derive(Debug, Clone)]
+    enum Step {
+        Line(String),
+        Wait(Duration),
+        Exit(i32),
+    }
+
+    fn line(text: &str) -> Step {
+        Step::Line(text.to_string())
+    }
+
+    /// A marker-mode reply the way CodeWalkerCli prints it, including its log lines.
+    fn marker_reply(output: &[&str]) -> Vec<Step> {
+        let mut steps = vec![line(PROCESSING_COMMAND_MSG), line("[INFO] Loading file"), line(OUTPUT_START_MSG)];
+        steps.extend(output.iter().map(|text| line(text)));
+        steps.extend([line(OUTPUT_END_MSG), line(COMMAND_COMPLETE_MSG)]);
+        steps
+    }
+
+    /// Stand-in for CodeWalkerCli: plays `startup` when launched, then answers each command
+    /// with whatever `script` returns for it.
+    struct MockLauncher {
+        startup: Vec<Step>,
+        script: fn(&str) -> Vec<Step>,
+        launches: AtomicUsize,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockLauncher {
+        fn new(startup: Vec<Step>, script: fn(&str) -> Vec<Step>) -> Arc<Self> {
+            Arc::new(Self {
+                startup,
+                script,
+                launches: AtomicUsize::new(0),
+                commands: Arc::new(Mutex::new(Vec::new())),
+            })
+        }
+
+        fn launches(&self) -> usize {
+            self.launches.load(Ordering::SeqCst)
+        }
+
+        fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    struct MockChild {
+        tx: mpsc::UnboundedSender<CliEvent>,
+        script: fn(&str) -> Vec<Step>,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    fn play(tx: mpsc::UnboundedSender<CliEvent>, steps: Vec<Step>) {
+        tauri::async_runtime::spawn(async move {
+            for step in steps {
+                match step {
+                    Step::Line(text) => {
+                        let _ = tx.send(CliEvent::Stdout(format!("{}\n", text)));
+                    }
+                    Step::Wait(delay) => tokio::time::sleep(delay).await,
+                    Step::Exit(code) => {
+                        let _ = tx.send(CliEvent::Terminated(Some(code)));
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    impl CliChild for MockChild {
+        fn write(&mut self, data: &[u8]) -> Result<(), CliError> {
+            let command = String::from_utf8_lossy(data).trim().to_string();
+            self.commands.lock().unwrap().push(command.clone());
+            play(self.tx.clone(), (self.script)(&command));
+            Ok(())
+        }
+
+        fn kill(self: Box<Self>) -> Result<(), CliError> {
+            Ok(())
+        }
+    }
+
+    impl CliLauncher for MockLauncher {
+        fn launch(&self, _gta_path: &str) -> Result<(Box<dyn CliChild>, mpsc::UnboundedReceiver<CliEvent>), CliError> {
+            self.launches.fetch_add(1, Ordering::SeqCst);
+            let (tx, rx) = mpsc::unbounded_channel();
+            play(tx.clone(), self.startup.clone());
+            let child = MockChild {
+                tx,
+                script: self.script,
+                commands: Arc::clone(&self.commands),
+            };
+            Ok((Box::new(child), rx))
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordedEvents(Mutex<Vec<(String, serde_json::Value)>>);
+
+    impl RecordedEvents {
+        fn payloads(&self, event: &str) -> Vec<serde_json::Value> {
+            let events = self.0.lock().unwrap();
+            events.iter().filter(|(name, _)| name == event).map(|(_, payload)| payload.clone()).collect()
+        }
+
+        fn states(&self) -> Vec<String> {
+            self.payloads("cli-status")
+                .iter()
+                .map(|status| status["state"].as_str().unwrap_or_default().to_string())
+                .collect()
+        }
+    }
+
+    impl CliEvents for RecordedEvents {
+        fn emit(&self, event: &str, payload: serde_json::Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    fn install(launcher: &Arc<MockLauncher>, auto_restart: bool) -> Arc<RecordedEvents> {
+        let events = Arc::new(RecordedEvents::default());
+        *lock_cli().unwrap() = None;
+        install_process("gta".to_string(), auto_restart, launcher.clone(), events.clone()).unwrap();
+        events
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out waiting for condition");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn state() -> String {
+        serde_json::to_value(get_cli_status().unwrap()).unwrap()["state"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn hello_script(command: &str) -> Vec<Step> {
+        match command {
+            "hello" => marker_reply(&["Hello"]),
+            "hang" => vec![line(PROCESSING_COMMAND_MSG)],
+            "crash" => vec![line(PROCESSING_COMMAND_MSG), Step::Wait(Duration::from_millis(100)), Step::Exit(3)],
+            _ => vec![line("[ERROR] Unknown command"), line(COMMAND_COMPLETE_MSG)],
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn commands_wait_until_ready() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![Step::Wait(Duration::from_millis(200)), line(READY)], hello_script);
+        let events = install(&launcher, false);
+        assert_eq!(state(), "starting");
+
+        let pending = tokio::spawn(send_command("hello".to_string(), None));
+        wait_until(|| !get_cli_queue().unwrap().is_empty()).await;
+        assert!(launcher.commands().is_empty());
+        assert!(!get_cli_queue().unwrap()[0].running);
+
+        assert_eq!(pending.await.unwrap().unwrap(), "Hello");
+        assert_eq!(state(), "ready");
+        assert_eq!(events.states(), ["starting", "ready"]);
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn output_is_sliced_between_markers() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line(READY)], |command| match command {
+            "export" => marker_reply(&["<Root>", "  <Item />", "", "</Root>"]),
+            _ => vec![line(PROCESSING_COMMAND_MSG), line("[INFO] No markers"), line("plain"), line(COMMAND_COMPLETE_MSG)],
+        });
+        install(&launcher, false);
+
+        let output = send_command("export".to_string(), None).await.unwrap();
+        assert_eq!(output, "<Root>\n<Item />\n</Root>");
+        let output = send_command("other".to_string(), None).await.unwrap();
+        assert_eq!(output, "plain");
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn commands_queue_while_busy() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line(READY)], |command| match command {
+            // Some exports report completion twice; the second must not complete the next command.
+            "slow" => {
+                let mut steps = vec![line(PROCESSING_COMMAND_MSG), Step::Wait(Duration::from_millis(200))];
+                steps.extend(marker_reply(&["slow"]).into_iter().skip(1));
+                steps.push(line(COMMAND_COMPLETE_MSG));
+                steps
+            }
+            _ => marker_reply(&[command]),
+        });
+        install(&launcher, false);
+        wait_until(|| state() == "ready").await;
+
+        let slow = tokio::spawn(send_command("slow".to_string(), None));
+        wait_until(|| launcher.commands().len() == 1).await;
+        let fast = tokio::spawn(send_command("fast".to_string(), None));
+        wait_until(|| get_cli_queue().unwrap().len() == 2).await;
+
+        let queue = get_cli_queue().unwrap();
+        assert_eq!((queue[0].command.as_str(), queue[0].running), ("slow", true));
+        assert_eq!((queue[1].command.as_str(), queue[1].running), ("fast", false));
+
+        assert_eq!(slow.await.unwrap().unwrap(), "slow");
+        assert_eq!(fast.await.unwrap().unwrap(), "fast");
+        assert_eq!(launcher.commands(), ["slow", "fast"]);
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancelled_commands_are_not_sent() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![Step::Wait(Duration::from_millis(100)), line(READY)], hello_script);
+        install(&launcher, false);
+
+        let pending = tokio::spawn(send_command("hello".to_string(), None));
+        wait_until(|| !get_cli_queue().unwrap().is_empty()).await;
+        let id = get_cli_queue().unwrap()[0].id;
+        assert!(cancel_command(id).unwrap());
+
+        assert!(matches!(pending.await.unwrap(), Err(CliError::Cancelled)));
+        assert_eq!(send_command("hello".to_string(), None).await.unwrap(), "Hello");
+        assert_eq!(launcher.commands(), ["hello"]);
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timeout_restarts_cli() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line(READY)], hello_script);
+        install(&launcher, false);
+
+        let result = send_command("hang".to_string(), Some(1)).await;
+        assert!(matches!(result, Err(CliError::Timeout(1))));
+        assert_eq!(launcher.launches(), 2);
+
+        assert_eq!(send_command("hello".to_string(), None).await.unwrap(), "Hello");
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timeout_while_not_ready() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line("[INFO] Loading cache")], hello_script);
+        install(&launcher, false);
+
+        let first = tokio::spawn(send_command("hello".to_string(), Some(1)));
+        let second = tokio::spawn(send_command("hello".to_string(), Some(1)));
+        assert!(matches!(first.await.unwrap(), Err(CliError::Timeout(1))));
+        assert!(matches!(second.await.unwrap(), Err(CliError::Timeout(1))));
+        assert!(get_cli_queue().unwrap().is_empty());
+        assert!(launcher.commands().is_empty());
+        assert_eq!(state(), "starting");
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn crash_fails_pending_and_restarts() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line(READY)], hello_script);
+        let events = install(&launcher, true);
+        wait_until(|| state() == "ready").await;
+
+        let crashed = tokio::spawn(send_command("crash".to_string(), None));
+        wait_until(|| launcher.commands().len() == 1).await;
+        let queued = tokio::spawn(send_command("hello".to_string(), None));
+        assert!(matches!(crashed.await.unwrap(), Err(CliError::Terminated(3))));
+        assert!(matches!(queued.await.unwrap(), Err(CliError::Terminated(3))));
+
+        wait_until(|| launcher.launches() == 2 && state() == "ready").await;
+        assert_eq!(events.states(), ["starting", "ready", "crashed", "restarting", "starting", "ready"]);
+        assert_eq!(send_command("hello".to_string(), None).await.unwrap(), "Hello");
+        stop_codewalker().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn crash_without_restart_stops() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line(READY)], hello_script);
+        install(&launcher, false);
+        wait_until(|| state() == "ready").await;
+
+        let result = send_command("crash".to_string(), None).await;
+        assert!(matches!(result, Err(CliError::Terminated(3))));
+        wait_until(|| state() == "stopped").await;
+        assert!(matches!(send_command("hello".to_string(), None).await, Err(CliError::NotRunning)));
+        assert_eq!(launcher.launches(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn json_protocol() {
+        let _serial = SERIAL.lock().await;
+        let launcher = MockLauncher::new(vec![line(r#"{"v":1,"type":"log","level":"info","message":"Cache initialized"}"#), line(JSON_READY)], |request| {
+            let request: serde_json::Value = serde_json::from_str(request).unwrap();
+            assert_eq!(request["v"], cli_protocol::PROTOCOL_VERSION);
+            let id = request["id"].as_u64().unwrap();
+            let result = match request["command"].as_str().unwrap() {
+                "hello" => format!(r#"{{"v":1,"type":"result","id":{},"status":"ok","payload":"[Hello]\n  world"}}"#, id),
+                _ => format!(r#"{{"v":1,"type":"result","id":{},"status":"error","error":"Unknown command"}}"#, id),
+            };
+            vec![
+                Step::Line(format!(r#"{{"v":1,"type":"started","id":{}}}"#, id)),
+                Step::Line(format!(r#"{{"v":1,"type":"progress","id":{},"done":1,"total":2,"message":"step"}}"#, id)),
+                // Newer protocol versions are rejected rather than misread.
+                Step::Line(format!(r#"{{"v":2,"type":"result","id":{},"status":"ok","payload":"wrong"}}"#, id)),
+                Step::Line(result),
+            ]
+        });
+        let events = install(&launcher, false);
+
+        assert_eq!(send_command("hello".to_string(), None).await.unwrap(), "[Hello]\n  world");
+        let error = send_command("nope".to_string(), None).await.unwrap_err();
+        assert_eq!(error.to_string(), "Command failed: Unknown command");
+
+        let sent: Vec<serde_json::Value> = launcher.commands().iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(sent[0]["command"], "hello");
+        assert_eq!(sent[1]["command"], "nope");
+        assert_eq!(events.payloads("cli-progress").len(), 2);
+        stop_codewalker().await.unwrap();
+    }
+}
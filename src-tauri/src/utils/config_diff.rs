@@ -0,0 +1,203 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::Path};
+use tauri::AppHandle;
+
+use super::files::collect_tables;
+use super::history::save_file;
+use super::path_access::check_path;
+use super::lua_writer::{apply_lua_edits, is_identifier, vector_components, LuaEdit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Only in the updated version.
+    Added,
+    /// Only in the current copy.
+    Removed,
+    TypeChanged,
+    ValueChanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub key_path: String,
+    pub kind: ChangeKind,
+    pub current: Option<Value>,
+    pub updated: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    pub content: String,
+    /// Key paths whose current value was written into the updated file.
+    pub applied: Vec<String>,
+    /// Changed key paths that couldn't be written, e.g. values computed at runtime.
+    pub skipped: Vec<String>,
+}
+
+fn is_vector(fields: &serde_json::Map<String, Value>) -> bool {
+    vector_components(fields).is_some()
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "nil",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(fields) if is_vector(fields) => "vector",
+        Value::Object(_) => "table",
+    }
+}
+
+fn child_path(parent: &str, key: &str) -> String {
+    if is_identifier(key) {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}[{}]", parent, key)
+    }
+}
+
+/// The children of a table value keyed by their path; arrays use 1-based `[i]` keys like Lua.
+fn children(path: &str, value: &Value) -> Option<BTreeMap<String, Value>> {
+    match value {
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (format!("{}[{}]", path, i + 1), item.clone()))
+                .collect(),
+        ),
+        Value::Object(fields) if !is_vector(fields) => Some(
+            fields
+                .iter()
+                .map(|(key, item)| (child_path(path, key), item.clone()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Numbers compare by value, so `10` and `10.0` are the same default.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, value)| b.get(key).is_some_and(|other| same_value(value, other)))
+        }
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b)),
+        _ => a == b,
+    }
+}
+
+fn diff_value(path: &str, current: &Value, updated: &Value, changes: &mut Vec<ConfigChange>) {
+    if type_name(current) != type_name(updated) {
+        changes.push(ConfigChange {
+            key_path: path.to_string(),
+            kind: ChangeKind::TypeChanged,
+            current: Some(current.clone()),
+            updated: Some(updated.clone()),
+        });
+        return;
+    }
+
+    match (children(path, current), children(path, updated)) {
+        (Some(current), Some(updated)) => diff_children(&current, &updated, changes),
+        _ if !same_value(current, updated) => changes.push(ConfigChange {
+            key_path: path.to_string(),
+            kind: ChangeKind::ValueChanged,
+            current: Some(current.clone()),
+            updated: Some(updated.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_children(current: &BTreeMap<String, Value>, updated: &BTreeMap<String, Value>, changes: &mut Vec<ConfigChange>) {
+    for (path, value) in current {
+        match updated.get(path) {
+            Some(other) => diff_value(path, value, other, changes),
+            None => changes.push(ConfigChange {
+                key_path: path.clone(),
+                kind: ChangeKind::Removed,
+                current: Some(value.clone()),
+                updated: None,
+            }),
+        }
+    }
+    for (path, value) in updated {
+        if !current.contains_key(path) {
+            changes.push(ConfigChange {
+                key_path: path.clone(),
+                kind: ChangeKind::Added,
+                current: None,
+                updated: Some(value.clone()),
+            });
+        }
+    }
+}
+
+/// Structural diff of the `table_filter` tables of two Lua config files, evaluated the same
+/// way `collect_tables` does. `current_path` is our copy, `updated_path` the new version.
+pub fn diff_configs(current_path: &Path, updated_path: &Path, table_filter: Vec<String>) -> Result<Vec<ConfigChange>, String> {
+    let current = collect_tables(current_path, table_filter.clone()).map_err(|e| format!("{}: {}", current_path.display(), e))?;
+    let updated = collect_tables(updated_path, table_filter).map_err(|e| format!("{}: {}", updated_path.display(), e))?;
+
+    let mut changes = Vec::new();
+    diff_children(&current.into_iter().collect(), &updated.into_iter().collect(), &mut changes);
+    changes.sort_by(|a, b| a.key_path.cmp(&b.key_path));
+    Ok(changes)
+}
+
+#[tauri::command]
+pub fn diff_lua_configs(
+    current_path: String,
+    updated_path: String,
+    table_filter: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<Vec<ConfigChange>, String> {
+    println!("Diffing Lua configs: {:?} -> {:?}", current_path, updated_path);
+    let current = check_path(&app_handle, &current_path)?;
+    let updated = check_path(&app_handle, &updated_path)?;
+    diff_configs(&current, &updated, table_filter)
+}
+
+/// Writes our changed values into the updated file, keeping its new keys and layout. Keys that
+/// changed type are left at the updated version since our value may no longer fit.
+#[tauri::command]
+pub fn merge_lua_configs(
+    current_path: String,
+    updated_path: String,
+    table_filter: Vec<String>,
+    dry_run: bool,
+    app_handle: AppHandle,
+) -> Result<MergeResult, String> {
+    println!("Merging Lua config values from {:?} into {:?}", current_path, updated_path);
+    let current = check_path(&app_handle, &current_path)?;
+    let file_path = &check_path(&app_handle, &updated_path)?;
+    let changes = diff_configs(&current, file_path, table_filter)?;
+    let mut content = fs::read_to_string(file_path).map_err(|e| format!("{}: {}", updated_path, e))?;
+
+    let mut applied = Vec::new();
+    let mut skipped = Vec::new();
+    for change in changes.into_iter().filter(|change| change.kind == ChangeKind::ValueChanged) {
+        let edit = LuaEdit {
+            key_path: change.key_path.clone(),
+            value: change.current.unwrap_or(Value::Null),
+        };
+        match apply_lua_edits(&content, &[edit]) {
+            Ok(rewritten) => {
+                content = rewritten;
+                applied.push(change.key_path);
+            }
+            Err(_) => skipped.push(change.key_path),
+        }
+    }
+
+    if !dry_run {
+        save_file(&app_handle, file_path, content.as_bytes())?;
+    }
+    Ok(MergeResult { content, applied, skipped })
+}
@@ -0,0 +1,318 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    hash::Hash,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+use tauri::AppHandle;
+
+use super::files::{collect_files, filter_duplicates};
+use super::path_access::{check_path, check_relative};
+use super::quarantine::{quarantine_files, QuarantineManifest};
+use super::resources::{ensure_order, start_position, ResourceResolver, STREAMED_EXTENSIONS};
+
+/// How many leading bytes the quick hash reads before files are fully hashed.
+const QUICK_HASH_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateMode {
+    /// Files that share a file name, wherever they are.
+    #[default]
+    Name,
+    /// Byte-identical files, whatever they are called.
+    Content,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    ExactCopy,
+    /// Same file name, but at least one of the files has different contents.
+    NameOnly,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    /// The shared file name, or for content groups the name of the first file.
+    pub name: String,
+    pub kind: DuplicateKind,
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+/// Which copy of a duplicate group survives a resolve; every other copy is quarantined.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum KeepPolicy {
+    Newest,
+    Largest,
+    /// The copy inside the resource with this name. Groups without one are skipped.
+    PreferredResource { resource: String },
+    /// The copy in the resource a server.cfg starts first. Groups with no started copy are skipped.
+    EnsureOrder { server_cfg: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamedAsset {
+    pub name: String,
+    pub paths: Vec<String>,
+}
+
+/// Assets streamed by both resources of a pair. Only one copy of each gets loaded.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamConflict {
+    pub resources: [String; 2],
+    /// The resource started last, whose copies are the ones the game ends up using. `None`
+    /// when neither resource is started by the supplied ensure order.
+    pub winner: Option<String>,
+    pub assets: Vec<StreamedAsset>,
+}
+
+fn quick_hash(path: &Path) -> io::Result<[u8; 32]> {
+    let mut buffer = Vec::new();
+    File::open(path)?.take(QUICK_HASH_BYTES).read_to_end(&mut buffer)?;
+    Ok(Sha256::digest(&buffer).into())
+}
+
+fn full_hash(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// Splits every group by `key`, keeping only the sub-groups that still hold more than one path.
+/// Files that can't be read are dropped.
+fn split_by<K, F>(base_path: &Path, groups: Vec<Vec<String>>, key: F) -> Vec<Vec<String>>
+where
+    K: Eq + Hash + Send,
+    F: Fn(&Path) -> io::Result<K> + Sync,
+{
+    groups
+        .into_par_iter()
+        .flat_map(|paths| {
+            let mut by_key: HashMap<K, Vec<String>> = HashMap::new();
+            let keyed: Vec<(K, String)> = paths
+                .into_par_iter()
+                .filter_map(|path| key(&base_path.join(&path)).ok().map(|k| (k, path)))
+                .collect();
+            for (k, path) in keyed {
+                by_key.entry(k).or_default().push(path);
+            }
+            by_key.into_values().filter(|paths| paths.len() > 1).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Groups byte-identical files: by size first, then by a hash of the first bytes, and only
+/// then by a hash of the whole file, so most files are never read in full.
+fn content_groups(base_path: &Path, paths: Vec<String>) -> Vec<Vec<String>> {
+    let by_size = split_by(base_path, vec![paths], |path| fs::metadata(path).map(|meta| meta.len()));
+    let by_quick_hash = split_by(base_path, by_size, quick_hash);
+    split_by(base_path, by_quick_hash, full_hash)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn file_size(base_path: &Path, path: &str) -> u64 {
+    fs::metadata(base_path.join(path)).map(|meta| meta.len()).unwrap_or_default()
+}
+
+pub fn find_duplicates(search_path: &Path, filter: &[String], mode: DuplicateMode) -> Vec<DuplicateGroup> {
+    let file_map = collect_files(search_path, filter);
+
+    let mut groups: Vec<DuplicateGroup> = match mode {
+        DuplicateMode::Name => filter_duplicates(file_map)
+            .into_par_iter()
+            .map(|(name, paths)| {
+                let copies = content_groups(search_path, paths.clone());
+                let kind = if copies.len() == 1 && copies[0].len() == paths.len() {
+                    DuplicateKind::ExactCopy
+                } else {
+                    DuplicateKind::NameOnly
+                };
+                DuplicateGroup {
+                    name,
+                    kind,
+                    size: file_size(search_path, &paths[0]),
+                    paths,
+                }
+            })
+            .collect(),
+        DuplicateMode::Content => {
+            let paths: Vec<String> = file_map.into_iter().flat_map(|(_, paths)| paths).collect();
+            content_groups(search_path, paths)
+                .into_iter()
+                .filter(|paths| file_size(search_path, &paths[0]) > 0)
+                .map(|mut paths| {
+                    paths.sort();
+                    DuplicateGroup {
+                        name: file_name(&paths[0]),
+                        kind: DuplicateKind::ExactCopy,
+                        size: file_size(search_path, &paths[0]),
+                        paths,
+                    }
+                })
+                .collect()
+        }
+    };
+
+    groups.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.paths.cmp(&b.paths)));
+    groups
+}
+
+fn resource_name(root: &Path) -> String {
+    root.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.to_string_lossy().to_string())
+}
+
+/// Streamed assets that more than one resource ships, grouped by the pair of resources that
+/// clash. Asset names are compared case-insensitively, like the game does.
+pub fn find_stream_conflicts(search_path: &Path, ensure_order: &[String]) -> Vec<StreamConflict> {
+    let extensions: Vec<String> = STREAMED_EXTENSIONS.iter().map(|ext| ext.to_string()).collect();
+    let mut resolver = ResourceResolver::new(search_path);
+
+    // Asset name -> resource root -> paths of that asset inside the resource.
+    let mut assets: BTreeMap<String, BTreeMap<PathBuf, Vec<String>>> = BTreeMap::new();
+    for (name, paths) in collect_files(search_path, &extensions) {
+        for path in paths {
+            if !resolver.is_streamed(&path) {
+                continue;
+            }
+            if let Some(root) = resolver.root_of(&path) {
+                assets
+                    .entry(name.to_lowercase())
+                    .or_default()
+                    .entry(root)
+                    .or_default()
+                    .push(path);
+            }
+        }
+    }
+
+    let mut conflicts: BTreeMap<(PathBuf, PathBuf), Vec<StreamedAsset>> = BTreeMap::new();
+    for (name, by_resource) in &assets {
+        let roots: Vec<&PathBuf> = by_resource.keys().collect();
+        for (i, first) in roots.iter().enumerate() {
+            for second in &roots[i + 1..] {
+                let mut paths = [by_resource[*first].clone(), by_resource[*second].clone()].concat();
+                paths.sort();
+                conflicts
+                    .entry(((*first).clone(), (*second).clone()))
+                    .or_default()
+                    .push(StreamedAsset { name: name.clone(), paths });
+            }
+        }
+    }
+
+    conflicts
+        .into_iter()
+        .map(|((first, second), assets)| {
+            let winner = match (start_position(ensure_order, &first), start_position(ensure_order, &second)) {
+                (Some(a), Some(b)) if a > b => Some(&first),
+                (Some(_), Some(_)) | (None, Some(_)) => Some(&second),
+                (Some(_), None) => Some(&first),
+                (None, None) => None,
+            };
+            StreamConflict {
+                resources: [resource_name(&first), resource_name(&second)],
+                winner: winner.map(|root| resource_name(root)),
+                assets,
+            }
+        })
+        .collect()
+}
+
+/// Index of the path with the greatest `key`; ties go to the first path.
+fn first_max_by<K: Ord>(base_path: &Path, paths: &[String], key: impl Fn(&fs::Metadata) -> Option<K>) -> Option<usize> {
+    paths
+        .iter()
+        .enumerate()
+        .filter_map(|(i, path)| fs::metadata(base_path.join(path)).ok().and_then(|meta| key(&meta)).map(|k| (i, k)))
+        .fold(None, |best: Option<(usize, K)>, (i, k)| match best {
+            Some((_, ref best_key)) if *best_key >= k => best,
+            _ => Some((i, k)),
+        })
+        .map(|(i, _)| i)
+}
+
+/// Index of the copy `policy` keeps out of `paths`, or `None` when it can't decide.
+fn keeper(
+    base_path: &Path,
+    paths: &[String],
+    policy: &KeepPolicy,
+    order: &[String],
+    resolver: &mut ResourceResolver,
+) -> Option<usize> {
+    match policy {
+        KeepPolicy::Newest => first_max_by(base_path, paths, |meta| meta.modified().ok()),
+        KeepPolicy::Largest => first_max_by(base_path, paths, |meta| Some(meta.len())),
+        KeepPolicy::PreferredResource { resource } => {
+            paths.iter().position(|path| resolver.name_of(path).as_deref() == Some(resource.as_str()))
+        }
+        KeepPolicy::EnsureOrder { .. } => paths
+            .iter()
+            .enumerate()
+            .filter_map(|(i, path)| resolver.root_of(path).and_then(|root| start_position(order, &root)).map(|p| (p, i)))
+            .min()
+            .map(|(_, i)| i),
+    }
+}
+
+/// Keeps one copy of each duplicate group according to `policy` and moves the others into
+/// quarantine. Groups are lists of paths relative to `path`, as returned by `find_duplicate_files`.
+/// Groups whose files aren't all byte-identical are skipped.
+#[tauri::command]
+pub fn resolve_duplicates(
+    path: String,
+    groups: Vec<Vec<String>>,
+    policy: KeepPolicy,
+    app_handle: AppHandle,
+) -> Result<QuarantineManifest, String> {
+    println!("Resolving {} duplicate groups with {:?} in: {:?}", groups.len(), policy, path);
+    let base_path = &check_path(&app_handle, &path)?;
+    let order = match &policy {
+        KeepPolicy::EnsureOrder { server_cfg } => {
+            let cfg = check_path(&app_handle, server_cfg)?;
+            ensure_order(&fs::read_to_string(cfg).map_err(|e| format!("{}: {}", server_cfg, e))?)
+        }
+        _ => Vec::new(),
+    };
+    for relative in groups.iter().flatten() {
+        check_relative(base_path, relative)?;
+    }
+    let mut resolver = ResourceResolver::new(base_path);
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for paths in groups.into_iter().filter(|paths| paths.len() > 1) {
+        // Files that only share a name are different assets; never quarantine one for another.
+        let copies = content_groups(base_path, paths.clone());
+        if copies.len() != 1 || copies[0].len() != paths.len() {
+            skipped.push(paths);
+            continue;
+        }
+        match keeper(base_path, &paths, &policy, &order, &mut resolver) {
+            Some(keep) => files.extend(
+                paths
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != keep)
+                    .map(|(_, path)| (path.clone(), paths[keep].clone())),
+            ),
+            None => skipped.push(paths),
+        }
+    }
+
+    quarantine_files(&app_handle, base_path, files, skipped)
+}
@@ -1,21 +1,23 @@
 use dashmap::DashMap;
 use rayon::prelude::*;
-use rlua::{Lua, Value};
 use std::ffi::OsStr;
 use std::path::Path;
 use std::fs;
-use walkdir::WalkDir;
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+
+use super::history;
+use super::lua_sandbox::LuaSandbox;
+use super::path_access::check_path;
+use super::resources::is_resource_root;
+use super::scan::scan_files;
 
 pub fn collect_files(search_path: &Path, filter: &[String]) -> DashMap<String, Vec<String>> {
     let file_map = DashMap::new();
 
-    WalkDir::new(search_path)
-        .into_iter()
-        .par_bridge()
-        .filter_map(Result::ok)
-        .filter(|entry| entry.file_type().is_file())
-        .filter_map(|entry| {
-            let path = entry.path();
+    scan_files(search_path)
+        .into_par_iter()
+        .filter_map(|path| {
             let file_name = path.file_name()?.to_str()?;
             let file_path = path
                 .strip_prefix(search_path)
@@ -26,7 +28,7 @@ pub fn collect_files(search_path: &Path, filter: &[String]) -> DashMap<String, V
             if filter.is_empty()
                 || filter
                     .iter()
-                    .any(|ext| path.extension().and_then(OsStr::to_str) == Some(ext))
+                    .any(|ext| path.extension().and_then(OsStr::to_str).is_some_and(|e| e.eq_ignore_ascii_case(ext)))
             {
                 Some((file_name.to_string(), file_path))
             } else {
@@ -58,29 +60,39 @@ pub fn filter_duplicates(file_map: DashMap<String, Vec<String>>) -> Vec<(String,
     results
 }
 
+/// The name of the resource `file_path` sits in, falling back to its folder name, for
+/// `GetCurrentResourceName()`.
+fn resource_name_of(file_path: &Path) -> String {
+    let dir = file_path.parent();
+    dir.and_then(|dir| dir.ancestors().find(|ancestor| is_resource_root(ancestor)))
+        .or(dir)
+        .and_then(|root| root.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Runs a Lua file and returns the contents of the requested global tables as JSON, keyed by
+/// name. Names that don't resolve to a table are left out.
 pub fn collect_tables(
     file_path: &Path,
     table_filter: Vec<String>,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+) -> Result<serde_json::Map<String, serde_json::Value>, Box<dyn std::error::Error>> {
     let content = fs::read_to_string(file_path)?;
-    let lua = Lua::new();
-    let mut table_names = Vec::new();
-
-    let globals = lua.globals();
-    lua.load(&content).exec()?;
+    let sandbox = LuaSandbox::new(&resource_name_of(file_path))?;
+    sandbox.exec(&file_path.to_string_lossy(), &content)?;
 
+    let mut tables = serde_json::Map::new();
     for table_name in table_filter {
-        let table: Value = globals.get::<_, Value>(table_name.as_str())?;
-        if table.is_table() {
-            table_names.push(table_name);
+        if let Some(table) = sandbox.table_json(&table_name) {
+            tables.insert(table_name, table);
         }
     }
 
-    Ok(table_names)
+    Ok(tables)
 }
 
 #[tauri::command]
-pub fn get_paths_in_dir(path: &str) -> Result<Vec<String>, String> {
+pub fn get_paths_in_dir(path: &str, app_handle: AppHandle) -> Result<Vec<String>, String> {
     println!("__________________________");
     println!("searching path: {:?}", path);
 
@@ -90,6 +102,7 @@ pub fn get_paths_in_dir(path: &str) -> Result<Vec<String>, String> {
     };
 
     println!("parent dir: {:?}", parent);
+    check_path(&app_handle, parent)?;
     let search_path = Path::new(parent);
 
     if !search_path.exists() {
@@ -125,25 +138,25 @@ pub fn get_paths_in_dir(path: &str) -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-pub fn read_file(path: &str) -> Result<String, String> {
-    match fs::read_to_string(path) {
+pub fn read_file(path: &str, app_handle: AppHandle) -> Result<String, String> {
+    match fs::read_to_string(check_path(&app_handle, path)?) {
         Ok(content) => Ok(content),
         Err(e) => Err(e.to_string()),
     }
 }
 
 #[tauri::command]
-pub fn read_binary_file(path: &str) -> Result<Vec<u8>, String> {
-    match std::fs::read(path) {
+pub fn read_binary_file(path: &str, app_handle: AppHandle) -> Result<Vec<u8>, String> {
+    match std::fs::read(check_path(&app_handle, path)?) {
         Ok(bytes) => Ok(bytes),
         Err(e) => Err(e.to_string()),
     }
 }
 
 #[tauri::command]
-pub fn write_file(path: &str, content: &str) -> Result<bool, String> {
+pub fn write_file(path: &str, content: &str, app_handle: AppHandle) -> Result<bool, String> {
     println!("writing to path: {:?}", path);
-    match fs::write(path, content) {
+    match history::save_file(&app_handle, &check_path(&app_handle, path)?, content.as_bytes()) {
         Ok(_) => Ok(true),
         Err(e) => {
             println!("error: {:?}", e);
@@ -152,9 +165,19 @@ pub fn write_file(path: &str, content: &str) -> Result<bool, String> {
     }
 }
 
+/// Opens a folder in the system file manager.
+#[tauri::command]
+pub fn open_folder(path: &str, app_handle: AppHandle) -> Result<(), String> {
+    let folder = check_path(&app_handle, path)?;
+    app_handle
+        .opener()
+        .open_path(folder.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-pub fn delete_file(path: &str) -> Result<(), String> {
-    match fs::remove_file(path) {
+pub fn delete_file(path: &str, app_handle: AppHandle) -> Result<(), String> {
+    match history::remove_file(&app_handle, &check_path(&app_handle, path)?) {
         Ok(_) => Ok(()),
         Err(e) => Err(e.to_string()),
     }
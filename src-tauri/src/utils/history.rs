@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+
+use super::path_access::{check_path, resolve};
+
+const HISTORY_DIR: &str = "history";
+const INDEX_FILE: &str = "index.json";
+/// Older versions of a file are dropped once it has this many.
+const MAX_VERSIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryReason {
+    Overwrite,
+    Delete,
+    Restore,
+}
+
+/// A previous version of a file, saved before it was overwritten or deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub path: String,
+    pub created: u64,
+    pub size: u64,
+    pub reason: HistoryReason,
+    /// Where the saved copy lives.
+    pub backup: String,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The resolved form of `path`, so the same file gets the same history however it was named.
+fn history_key(path: &Path) -> String {
+    resolve(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Each file's versions live in a folder named after the hash of its absolute path.
+fn history_dir(app_handle: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let hash = Sha256::digest(history_key(path).as_bytes());
+    let name: String = hash[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(HISTORY_DIR)
+        .join(name))
+}
+
+fn read_index(dir: &Path) -> Vec<HistoryEntry> {
+    fs::read_to_string(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(dir: &Path, entries: &[HistoryEntry]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    write_atomic(&dir.join(INDEX_FILE), json.as_bytes())
+}
+
+/// Writes `content` to a temporary file next to `path`, then renames it over `path`, so a crash
+/// or full disk never leaves a half-written file behind.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("{}: not a file path", path.display()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("{}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+/// Copies the current contents of `path` into its history. Returns `None` when there is no file
+/// to save yet.
+pub fn backup(app_handle: &AppHandle, path: &Path, reason: HistoryReason) -> Result<Option<HistoryEntry>, String> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(None);
+    };
+    let dir = history_dir(app_handle, path)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let mut entries = read_index(&dir);
+
+    // Two saves within the same millisecond still need their own ids.
+    let mut created = now_millis();
+    while entries.iter().any(|entry| entry.created == created) {
+        created += 1;
+    }
+    let id = created.to_string();
+    let backup = dir.join(&id);
+    fs::copy(path, &backup).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let entry = HistoryEntry {
+        id,
+        path: history_key(path),
+        created,
+        size: metadata.len(),
+        reason,
+        backup: backup.to_string_lossy().to_string(),
+    };
+    entries.push(entry.clone());
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created));
+    for dropped in entries.drain(entries.len().min(MAX_VERSIONS)..) {
+        let _ = fs::remove_file(&dropped.backup);
+    }
+    write_index(&dir, &entries)?;
+
+    Ok(Some(entry))
+}
+
+/// Saves the current version of `path` to its history, then atomically replaces it with
+/// `content`. Nothing is written or saved when the content is unchanged.
+pub fn save_file(app_handle: &AppHandle, path: &Path, content: &[u8]) -> Result<Option<HistoryEntry>, String> {
+    if fs::read(path).is_ok_and(|current| current == content) {
+        return Ok(None);
+    }
+    let entry = backup(app_handle, path, HistoryReason::Overwrite)?;
+    write_atomic(path, content)?;
+    Ok(entry)
+}
+
+/// Saves `path` to its history before deleting it.
+pub fn remove_file(app_handle: &AppHandle, path: &Path) -> Result<Option<HistoryEntry>, String> {
+    let entry = backup(app_handle, path, HistoryReason::Delete)?;
+    fs::remove_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(entry)
+}
+
+/// Saves the current version of a file before something outside this module overwrites it,
+/// such as a CodeWalker import.
+#[tauri::command]
+pub fn backup_file(path: String, app_handle: AppHandle) -> Result<Option<HistoryEntry>, String> {
+    backup(&app_handle, &check_path(&app_handle, &path)?, HistoryReason::Overwrite)
+}
+
+/// Saved versions of a file, newest first.
+#[tauri::command]
+pub fn list_file_history(path: String, app_handle: AppHandle) -> Result<Vec<HistoryEntry>, String> {
+    let dir = history_dir(&app_handle, &check_path(&app_handle, &path)?)?;
+    Ok(read_index(&dir))
+}
+
+/// Puts a saved version back in place. The version being replaced is saved first, so a restore
+/// can itself be undone.
+#[tauri::command]
+pub fn restore_file_version(path: String, id: String, app_handle: AppHandle) -> Result<HistoryEntry, String> {
+    println!("Restoring {:?} to version {:?}", path, id);
+    let file_path = &check_path(&app_handle, &path)?;
+    let dir = history_dir(&app_handle, file_path)?;
+    let entry = read_index(&dir)
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| format!("{}: no saved version {}", path, id))?;
+    // The index can be edited like any file in the app's data, so only restore copies kept here.
+    if !resolve(Path::new(&entry.backup)).is_ok_and(|backup| resolve(&dir).is_ok_and(|dir| backup.starts_with(dir))) {
+        return Err(format!("{} is outside the file's history", entry.backup));
+    }
+    let content = fs::read(&entry.backup).map_err(|e| format!("{}: {}", entry.backup, e))?;
+
+    backup(&app_handle, file_path, HistoryReason::Restore)?;
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    write_atomic(file_path, &content)?;
+    Ok(entry)
+}
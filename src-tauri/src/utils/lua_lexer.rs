@@ -0,0 +1,159 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Name,
+    Number,
+    String,
+    Comment,
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offset of the first character of the token in the source.
+    pub start: usize,
+}
+
+impl<'a> Token<'a> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == TokenKind::Symbol && self.text == symbol
+    }
+
+    /// The text inside a comment, without the `--` and any long-bracket delimiters,
+    /// together with the byte offset where that text starts in the source.
+    pub fn comment_body(&self) -> Option<(&'a str, usize)> {
+        if self.kind != TokenKind::Comment {
+            return None;
+        }
+        let rest = &self.text[2..];
+        match long_bracket_level(rest) {
+            Some(level) => {
+                let delimiter = level + 2;
+                let close = format!("]{}]", "=".repeat(level));
+                let body = &rest[delimiter..];
+                let body = body.strip_suffix(close.as_str()).unwrap_or(body);
+                Some((body, self.start + 2 + delimiter))
+            }
+            None => Some((rest, self.start + 2)),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 10] = ["...", "..", "==", "~=", "<=", ">=", "::", "//", "<<", ">>"];
+
+/// Returns the level of a long bracket (`[[` is 0, `[==[` is 2) if `s` starts with one.
+fn long_bracket_level(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    if bytes.first() != Some(&b'[') {
+        return None;
+    }
+    let level = bytes[1..].iter().take_while(|&&b| b == b'=').count();
+    (bytes.get(level + 1) == Some(&b'[')).then_some(level)
+}
+
+/// Length of a long bracket string or comment body starting at `s`, including both delimiters.
+/// Unterminated brackets run to the end of the input.
+fn long_bracket_len(s: &str, level: usize) -> usize {
+    let close = format!("]{}]", "=".repeat(level));
+    let open_len = level + 2;
+    s[open_len..]
+        .find(&close)
+        .map_or(s.len(), |i| open_len + i + close.len())
+}
+
+fn quoted_string_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let quote = bytes[0];
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' => return i,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let hex = bytes.len() > 1 && bytes[0] == b'0' && (bytes[1] == b'x' || bytes[1] == b'X');
+    let mut i = if hex { 2 } else { 0 };
+    while i < bytes.len() {
+        let b = bytes[i];
+        let exponent = if hex { b == b'p' || b == b'P' } else { b == b'e' || b == b'E' };
+        if exponent && matches!(bytes.get(i + 1), Some(b'+' | b'-')) {
+            i += 2;
+        } else if b.is_ascii_alphanumeric() || b == b'.' {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+/// Splits Lua source into tokens, keeping comments so callers can decide what to do with them.
+/// Whitespace is dropped; anything unrecognised becomes a one-character symbol.
+pub fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let bytes = src.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        let rest = &src[i..];
+
+        let (kind, len) = if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if let Some(comment) = rest.strip_prefix("--") {
+            let len = match long_bracket_level(comment) {
+                Some(level) => 2 + long_bracket_len(comment, level),
+                None => rest.find('\n').unwrap_or(rest.len()),
+            };
+            (TokenKind::Comment, len)
+        } else if b == b'"' || b == b'\'' {
+            (TokenKind::String, quoted_string_len(rest))
+        } else if let Some(level) = long_bracket_level(rest) {
+            (TokenKind::String, long_bracket_len(rest, level))
+        } else if b.is_ascii_digit() || (b == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            (TokenKind::Number, number_len(rest))
+        } else if b.is_ascii_alphabetic() || b == b'_' {
+            let len = rest
+                .bytes()
+                .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+                .count();
+            (TokenKind::Name, len)
+        } else {
+            let len = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .map_or_else(|| rest.chars().next().map_or(1, char::len_utf8), |symbol| symbol.len());
+            (TokenKind::Symbol, len)
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &src[i..i + len],
+            start: i,
+        });
+        i += len;
+    }
+
+    tokens
+}
+
+/// Parses a Lua numeric literal, including hex integers.
+pub fn parse_number(text: &str) -> Option<f64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return i64::from_str_radix(hex, 16).ok().map(|n| n as f64);
+    }
+    text.parse().ok()
+}
@@ -0,0 +1,355 @@
+use super::lua_lexer::{tokenize, Token, TokenKind};
+
+const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+const BINARY_OPERATORS: [&str; 21] = [
+    "+", "-", "*", "/", "//", "%", "^", "..", "==", "~=", "<", "<=", ">", ">=", "and", "or", "&", "|",
+    "~", "<<", ">>",
+];
+
+/// A value in Lua source that can be addressed by a key path, such as `Config.Shops[2].coords`.
+///
+/// Token indexes refer to the comment-free token list the field was parsed from.
+#[derive(Debug, Clone)]
+pub struct LuaField {
+    pub key_path: String,
+    /// Index of the first token of the value.
+    pub value_start: usize,
+    /// Index one past the last token of the value.
+    pub value_end: usize,
+}
+
+pub fn is_keyword(token: &Token) -> bool {
+    token.kind == TokenKind::Name && KEYWORDS.contains(&token.text)
+}
+
+fn is_symbol(tokens: &[Token], i: usize, symbol: &str) -> bool {
+    tokens.get(i).is_some_and(|token| token.is_symbol(symbol))
+}
+
+fn is_name(tokens: &[Token], i: usize) -> bool {
+    tokens.get(i).is_some_and(|token| token.kind == TokenKind::Name && !is_keyword(token))
+}
+
+fn is_word(tokens: &[Token], i: usize, word: &str) -> bool {
+    tokens.get(i).is_some_and(|token| token.kind == TokenKind::Name && token.text == word)
+}
+
+/// Index one past the bracket that closes the one at `open`.
+fn matching_close(tokens: &[Token], open: usize) -> usize {
+    let (open_symbol, close_symbol) = match tokens[open].text {
+        "(" => ("(", ")"),
+        "[" => ("[", "]"),
+        _ => ("{", "}"),
+    };
+
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.is_symbol(open_symbol) {
+            depth += 1;
+        } else if token.is_symbol(close_symbol) {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+    }
+    tokens.len()
+}
+
+/// Index one past the `end` that closes the block opened by the keyword at `start`.
+fn matching_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        if token.kind != TokenKind::Name {
+            continue;
+        }
+        match token.text {
+            "function" | "do" | "if" | "repeat" => depth += 1,
+            "end" | "until" => {
+                depth -= 1;
+                if depth == 0 {
+                    return if token.text == "until" { expression_end(tokens, i + 1) } else { i + 1 };
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// Index one past the end of the expression starting at `start`.
+pub fn expression_end(tokens: &[Token], start: usize) -> usize {
+    let mut i = start;
+    loop {
+        while is_symbol(tokens, i, "-") || is_symbol(tokens, i, "#") || is_symbol(tokens, i, "~") || is_word(tokens, i, "not") {
+            i += 1;
+        }
+
+        let Some(token) = tokens.get(i) else {
+            return i;
+        };
+        i = match token.kind {
+            TokenKind::Number | TokenKind::String => i + 1,
+            TokenKind::Name if token.text == "function" => matching_end(tokens, i),
+            TokenKind::Name if matches!(token.text, "nil" | "true" | "false") => i + 1,
+            TokenKind::Name if is_keyword(token) => return i,
+            TokenKind::Name => i + 1,
+            TokenKind::Symbol if token.text == "{" || token.text == "(" => matching_close(tokens, i),
+            TokenKind::Symbol if token.text == "..." => i + 1,
+            _ => return i,
+        };
+
+        // Field access, method calls and call arguments.
+        loop {
+            if (is_symbol(tokens, i, ".") || is_symbol(tokens, i, ":")) && is_name(tokens, i + 1) {
+                i += 2;
+            } else if is_symbol(tokens, i, "[") || is_symbol(tokens, i, "(") || is_symbol(tokens, i, "{") {
+                i = matching_close(tokens, i);
+            } else if tokens.get(i).is_some_and(|token| token.kind == TokenKind::String) {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        match tokens.get(i) {
+            Some(token)
+                if (token.kind == TokenKind::Symbol || token.kind == TokenKind::Name)
+                    && BINARY_OPERATORS.contains(&token.text) =>
+            {
+                i += 1
+            }
+            _ => return i,
+        }
+    }
+}
+
+/// The dotted name ending at token `end` (inclusive), e.g. `Config.Shops[2]` or `exports.ox_target:addBoxZone`.
+pub fn path_ending_at(tokens: &[Token], end: usize) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut i = Some(end);
+
+    while let Some(idx) = i {
+        if is_name(tokens, idx) {
+            parts.push(tokens[idx].text.to_string());
+            i = idx.checked_sub(1);
+        } else if is_symbol(tokens, idx, "]") && idx >= 2 && is_symbol(tokens, idx - 2, "[") {
+            // Only literal keys like `[2]` or `["name"]` are part of a path.
+            let key = &tokens[idx - 1];
+            if key.kind != TokenKind::Number && key.kind != TokenKind::String {
+                break;
+            }
+            parts.push(format!("[{}]", key.text));
+            i = idx.checked_sub(3);
+            // An index can follow a name or another index directly.
+            if i.is_some_and(|prev| is_name(tokens, prev) || is_symbol(tokens, prev, "]")) {
+                continue;
+            }
+        } else {
+            break;
+        }
+
+        match i {
+            Some(sep) if is_symbol(tokens, sep, ".") || is_symbol(tokens, sep, ":") => {
+                parts.push(tokens[sep].text.to_string());
+                i = sep.checked_sub(1);
+            }
+            _ => break,
+        }
+    }
+
+    // A path has to start with a name; `.x` or `[1]` on their own are not addressable.
+    if !parts.last().is_some_and(|part| is_identifier(part)) {
+        return None;
+    }
+    parts.reverse();
+    Some(parts.concat())
+}
+
+fn is_identifier(part: &str) -> bool {
+    part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// Rewrites a key path into one canonical spelling so paths typed by hand match parsed ones:
+/// `Config["Shops"][1]` and `Config.Shops[1]` both become `Config.Shops[1]`, and string keys
+/// that aren't identifiers lose their quotes.
+pub fn normalize_path(path: &str) -> String {
+    let tokens = tokenize(path);
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if is_name(&tokens, i) {
+            if !out.is_empty() {
+                out.push('.');
+            }
+            out.push_str(token.text);
+        } else if token.is_symbol("[") {
+            let Some(close) = (i + 1..tokens.len()).find(|&j| tokens[j].is_symbol("]")) else {
+                break;
+            };
+            let inner = path[token.end()..tokens[close].start].trim();
+            let quoted = close == i + 2 && tokens[i + 1].kind == TokenKind::String;
+            let key = if quoted { unquote(inner) } else { inner };
+            let identifier = quoted
+                && is_identifier(key)
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !KEYWORDS.contains(&key);
+            if identifier {
+                out.push('.');
+                out.push_str(key);
+            } else {
+                out.push_str(&format!("[{}]", key));
+            }
+            i = close;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// The contents of a quoted or long-bracket string literal, without its delimiters.
+pub fn unquote(literal: &str) -> &str {
+    if let Some(rest) = literal.strip_prefix('[') {
+        let level = rest.chars().take_while(|c| *c == '=').count();
+        let delimiter = level + 2;
+        literal.get(delimiter..literal.len().saturating_sub(delimiter)).unwrap_or_default()
+    } else {
+        literal.get(1..literal.len().saturating_sub(1)).unwrap_or_default()
+    }
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() || key.starts_with('[') {
+        format!("{}{}", parent, key)
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Parses the table constructor at `open` and pushes every field (and nested field) under `path`.
+fn table_fields(tokens: &[Token], open: usize, path: &str, fields: &mut Vec<LuaField>) -> usize {
+    let close = matching_close(tokens, open);
+    let mut i = open + 1;
+    let mut position = 1;
+
+    while i < close - 1 {
+        let (key, value_start) = if is_name(tokens, i) && is_symbol(tokens, i + 1, "=") {
+            (tokens[i].text.to_string(), i + 2)
+        } else if is_symbol(tokens, i, "[") {
+            let key_end = matching_close(tokens, i);
+            if !is_symbol(tokens, key_end, "=") {
+                break;
+            }
+            let key = tokens[i + 1..key_end - 1].iter().map(|token| token.text).collect::<String>();
+            (format!("[{}]", key), key_end + 1)
+        } else {
+            let key = format!("[{}]", position);
+            position += 1;
+            (key, i)
+        };
+
+        let value_end = expression_end(tokens, value_start).min(close - 1);
+        let key_path = join_path(path, &key);
+        fields.push(LuaField {
+            key_path: key_path.clone(),
+            value_start,
+            value_end,
+        });
+        if is_symbol(tokens, value_start, "{") {
+            table_fields(tokens, value_start, &key_path, fields);
+        }
+
+        i = value_end.max(value_start + 1);
+        if is_symbol(tokens, i, ",") || is_symbol(tokens, i, ";") {
+            i += 1;
+        } else if i < close - 1 {
+            // Malformed or unsupported field syntax; skip to the next separator.
+            while i < close - 1 && !is_symbol(tokens, i, ",") && !is_symbol(tokens, i, ";") {
+                i = if is_symbol(tokens, i, "{") || is_symbol(tokens, i, "(") || is_symbol(tokens, i, "[") {
+                    matching_close(tokens, i)
+                } else {
+                    i + 1
+                };
+            }
+            i += 1;
+        }
+    }
+
+    close
+}
+
+/// Every addressable value in a comment-free token list: assignment targets like
+/// `Config.Shops = {...}` and all fields of table constructors, including anonymous tables
+/// passed straight to calls, whose keys are then relative to the table.
+pub fn fields(tokens: &[Token]) -> Vec<LuaField> {
+    let mut fields = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if tokens[i].is_symbol("=") && i > 0 {
+            let value_start = i + 1;
+            let value_end = expression_end(tokens, value_start);
+            if let Some(key_path) = path_ending_at(tokens, i - 1) {
+                fields.push(LuaField {
+                    key_path: key_path.clone(),
+                    value_start,
+                    value_end,
+                });
+                if is_symbol(tokens, value_start, "{") {
+                    i = table_fields(tokens, value_start, &key_path, &mut fields);
+                    continue;
+                }
+            }
+            i += 1;
+        } else if tokens[i].is_symbol("{") {
+            i = table_fields(tokens, i, "", &mut fields);
+        } else {
+            i += 1;
+        }
+    }
+
+    fields
+}
+
+/// The innermost field whose value contains token `i`.
+pub fn field_at(fields: &[LuaField], i: usize) -> Option<&LuaField> {
+    fields
+        .iter()
+        .filter(|field| field.value_start <= i && i < field.value_end)
+        .max_by_key(|field| field.value_start)
+}
+
+/// For each token index in `at` (ascending), the name of the innermost function call whose
+/// arguments contain it, ignoring calls for which `skip` returns true.
+pub fn enclosing_calls(tokens: &[Token], at: &[usize], skip: impl Fn(&str) -> bool) -> Vec<Option<String>> {
+    let mut calls: Vec<Option<String>> = Vec::new();
+    let mut results = Vec::with_capacity(at.len());
+    let mut next = 0;
+
+    for (j, token) in tokens.iter().enumerate() {
+        while at.get(next) == Some(&j) {
+            results.push(calls.iter().rev().flatten().find(|name| !skip(name)).cloned());
+            next += 1;
+        }
+        if next == at.len() {
+            break;
+        }
+
+        if token.is_symbol("(") {
+            let is_call = j > 0
+                && (is_name(tokens, j - 1) || is_symbol(tokens, j - 1, ")") || is_symbol(tokens, j - 1, "]"));
+            calls.push(if is_call { path_ending_at(tokens, j - 1) } else { None });
+        } else if token.is_symbol(")") {
+            calls.pop();
+        }
+    }
+
+    results.resize(at.len(), None);
+    results
+}
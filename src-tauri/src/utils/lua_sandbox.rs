@@ -0,0 +1,282 @@
+use rlua::{HookTriggers, Lua, LuaOptions, StdLib, Table, Value};
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+const MAX_INSTRUCTIONS: u64 = 100_000_000;
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// How many VM instructions run between limit checks.
+const HOOK_INTERVAL: u32 = 10_000;
+
+/// Stand-ins for the globals FiveM, ox_lib, ESX and QBCore provide, so config files can be run
+/// outside the game. Anything reached through `exports`, `lib` or `cache` is a stub that can be
+/// indexed and called without erroring. Vectors are tables tagged with `__vector`.
+const PRELUDE: &str = r#"
+local stub_meta = { __stub = true }
+local function stub() return setmetatable({}, stub_meta) end
+stub_meta.__index = function() return stub() end
+stub_meta.__call = function() return stub() end
+stub_meta.__tostring = function() return "" end
+stub_meta.__concat = function(a, b) return tostring(a) .. tostring(b) end
+
+local vector_meta = { __vector = true }
+local axes = { "x", "y", "z", "w" }
+local function combine(a, b, op)
+    local out = {}
+    for _, axis in ipairs(axes) do
+        local left = type(a) == "number" and a or a[axis]
+        local right = type(b) == "number" and b or b[axis]
+        if left ~= nil and right ~= nil then out[axis] = op(left, right) end
+    end
+    return setmetatable(out, vector_meta)
+end
+vector_meta.__add = function(a, b) return combine(a, b, function(x, y) return x + y end) end
+vector_meta.__sub = function(a, b) return combine(a, b, function(x, y) return x - y end) end
+vector_meta.__mul = function(a, b) return combine(a, b, function(x, y) return x * y end) end
+vector_meta.__div = function(a, b) return combine(a, b, function(x, y) return x / y end) end
+vector_meta.__unm = function(a) return combine(a, -1, function(x, y) return x * y end) end
+
+function vector2(x, y) return setmetatable({ x = x, y = y }, vector_meta) end
+function vector3(x, y, z) return setmetatable({ x = x, y = y, z = z }, vector_meta) end
+function vector4(x, y, z, w) return setmetatable({ x = x, y = y, z = z, w = w }, vector_meta) end
+function vec(x, y, z, w) return setmetatable({ x = x, y = y, z = z, w = w }, vector_meta) end
+vec2, vec3, vec4 = vector2, vector3, vector4
+
+local function noop() end
+Citizen = { CreateThread = noop, Wait = noop, SetTimeout = noop, Trace = noop, InvokeNative = stub }
+CreateThread, Wait, SetTimeout = noop, noop, noop
+RegisterNetEvent, RegisterServerEvent, AddEventHandler, RemoveEventHandler = noop, noop, noop, noop
+TriggerEvent, TriggerServerEvent, TriggerClientEvent, TriggerLatentClientEvent = noop, noop, noop, noop
+RegisterCommand, RegisterKeyMapping, RegisterNUICallback, SendNUIMessage = noop, noop, noop, noop
+function IsDuplicityVersion() return false end
+function GetConvar(_, default) return default end
+GetConvarInt = GetConvar
+function GetResourceState() return "started" end
+function LoadResourceFile() return nil end
+function GetGameTimer() return 0 end
+function PlayerPedId() return 0 end
+function GetEntityCoords() return vector3(0.0, 0.0, 0.0) end
+
+exports = setmetatable({}, { __index = function() return stub() end, __call = noop })
+lib = stub()
+cache = stub()
+function locale(key) return key end
+function _U(key) return key end
+Locales = {}
+"#;
+
+/// A Lua state for running resource files: no `os`, `io`, `package` or `debug`, no loading of
+/// other files, FiveM globals stubbed out, and hard limits on instructions, memory and time.
+pub struct LuaSandbox {
+    lua: Lua,
+}
+
+impl LuaSandbox {
+    pub fn new(resource_name: &str) -> rlua::Result<Self> {
+        let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8 | StdLib::COROUTINE;
+        let lua = Lua::new_with(libs, LuaOptions::default())?;
+        lua.set_memory_limit(MEMORY_LIMIT)?;
+        install_globals(&lua, resource_name)?;
+        Ok(Self { lua })
+    }
+
+    /// Runs `source`, stopping it with an error once it goes over the instruction or time limit.
+    pub fn exec(&self, name: &str, source: &str) -> rlua::Result<()> {
+        let started = Instant::now();
+        let instructions = AtomicU64::new(0);
+        self.lua.set_hook(
+            HookTriggers::new().every_nth_instruction(HOOK_INTERVAL),
+            move |_, _| {
+                let count = instructions.fetch_add(HOOK_INTERVAL as u64, Ordering::Relaxed);
+                if count > MAX_INSTRUCTIONS {
+                    Err(rlua::Error::RuntimeError(format!("instruction limit of {} exceeded", MAX_INSTRUCTIONS)))
+                } else if started.elapsed() > TIMEOUT {
+                    Err(rlua::Error::RuntimeError(format!("timed out after {:?}", TIMEOUT)))
+                } else {
+                    Ok(())
+                }
+            },
+        );
+
+        let result = self.lua.load(source).set_name(format!("@{}", name)).exec();
+        self.lua.remove_hook();
+        result
+    }
+
+    /// The global table at a dotted name such as `Config.Shops`, converted to JSON.
+    pub fn table_json(&self, name: &str) -> Option<serde_json::Value> {
+        let mut parts = name.split('.');
+        let mut value: Value = self.lua.globals().get(parts.next()?).ok()?;
+        for part in parts {
+            let Value::Table(table) = value else {
+                return None;
+            };
+            value = table.get(part).ok()?;
+        }
+        match value {
+            Value::Table(table) if !has_meta_flag(&table, "__stub") => Some(table_to_json(&table, &mut Vec::new())),
+            _ => None,
+        }
+    }
+}
+
+/// FiveM's `GetHashKey`: Jenkins one-at-a-time over the lowercased name, as a signed integer.
+fn joaat(name: &str) -> i32 {
+    let mut hash: u32 = 0;
+    for byte in name.to_lowercase().bytes() {
+        hash = hash.wrapping_add(byte as u32);
+        hash = hash.wrapping_add(hash << 10);
+        hash ^= hash >> 6;
+    }
+    hash = hash.wrapping_add(hash << 3);
+    hash ^= hash >> 11;
+    hash = hash.wrapping_add(hash << 15);
+    hash as i32
+}
+
+fn install_globals(lua: &Lua, resource_name: &str) -> rlua::Result<()> {
+    let globals = lua.globals();
+    for name in ["dofile", "loadfile", "load"] {
+        globals.raw_set(name, Value::Nil)?;
+    }
+    globals.get::<_, Table>("string")?.raw_set("dump", Value::Nil)?;
+
+    let hash = lua.create_function(|_, name: String| Ok(joaat(&name)))?;
+    globals.set("GetHashKey", hash.clone())?;
+    globals.set("joaat", hash)?;
+
+    let resource_name = resource_name.to_string();
+    globals.set(
+        "GetCurrentResourceName",
+        lua.create_function(move |_, ()| Ok(resource_name.clone()))?,
+    )?;
+
+    let json = lua.create_table()?;
+    json.set(
+        "encode",
+        lua.create_function(|_, value: Value| {
+            serde_json::to_string(&lua_to_json(&value, &mut Vec::new())).map_err(rlua::Error::external)
+        })?,
+    )?;
+    json.set(
+        "decode",
+        lua.create_function(|lua, text: String| {
+            let value: serde_json::Value = serde_json::from_str(&text).map_err(rlua::Error::external)?;
+            json_to_lua(lua, &value)
+        })?,
+    )?;
+    globals.set("json", json)?;
+
+    let print = lua.create_function(|_, values: rlua::Variadic<Value>| {
+        let parts: Vec<String> = values.iter().map(|value| lua_to_json(value, &mut Vec::new()).to_string()).collect();
+        println!("[lua] {}", parts.join(" "));
+        Ok(())
+    })?;
+    globals.set("print", print)?;
+
+    lua.load(PRELUDE).set_name("=prelude").exec()
+}
+
+fn has_meta_flag(table: &Table, flag: &str) -> bool {
+    table
+        .get_metatable()
+        .is_some_and(|meta| meta.raw_get::<_, bool>(flag).unwrap_or(false))
+}
+
+fn json_key(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.to_string_lossy().to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        other => format!("<{}>", other.type_name()),
+    }
+}
+
+/// Converts a table to a JSON array when its keys are exactly `1..=n`, and to an object otherwise.
+/// `parents` holds the tables currently being converted, so a table nested inside itself comes
+/// out as `"<cycle>"` instead of recursing forever.
+fn table_to_json(table: &Table, parents: &mut Vec<*const c_void>) -> serde_json::Value {
+    let pointer = table.to_pointer();
+    if parents.contains(&pointer) {
+        return serde_json::Value::String("<cycle>".to_string());
+    }
+    parents.push(pointer);
+    let json = table_contents_to_json(table, parents);
+    parents.pop();
+    json
+}
+
+fn table_contents_to_json(table: &Table, parents: &mut Vec<*const c_void>) -> serde_json::Value {
+    if has_meta_flag(table, "__stub") {
+        return serde_json::Value::Null;
+    }
+    if has_meta_flag(table, "__vector") {
+        let components = ["x", "y", "z", "w"]
+            .iter()
+            .filter_map(|axis| match table.raw_get::<_, Value>(*axis) {
+                Ok(Value::Nil) | Err(_) => None,
+                Ok(value) => Some((axis.to_string(), lua_to_json(&value, parents))),
+            })
+            .collect();
+        return serde_json::Value::Object(components);
+    }
+
+    let pairs: Vec<(Value, Value)> = table.clone().pairs::<Value, Value>().filter_map(Result::ok).collect();
+    let len = table.raw_len();
+    if len > 0 && pairs.len() == len {
+        return serde_json::Value::Array(
+            (1..=len)
+                .map(|i| lua_to_json(&table.raw_get::<_, Value>(i).unwrap_or(Value::Nil), parents))
+                .collect(),
+        );
+    }
+
+    serde_json::Value::Object(
+        pairs
+            .iter()
+            .map(|(key, value)| (json_key(key), lua_to_json(value, parents)))
+            .collect(),
+    )
+}
+
+fn lua_to_json(value: &Value, parents: &mut Vec<*const c_void>) -> serde_json::Value {
+    match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::Boolean(b) => serde_json::Value::Bool(*b),
+        Value::Integer(i) => serde_json::Value::from(*i),
+        Value::Number(n) => serde_json::Number::from_f64(*n).map_or(serde_json::Value::Null, serde_json::Value::Number),
+        Value::String(s) => serde_json::Value::String(s.to_string_lossy().to_string()),
+        Value::Table(table) => table_to_json(table, parents),
+        other => serde_json::Value::String(format!("<{}>", other.type_name())),
+    }
+}
+
+fn json_to_lua<'lua>(lua: &'lua Lua, value: &serde_json::Value) -> rlua::Result<Value<'lua>> {
+    Ok(match value {
+        serde_json::Value::Null => Value::Nil,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Number(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::String(lua.create_string(s)?),
+        serde_json::Value::Array(items) => {
+            let table = lua.create_table()?;
+            for (i, item) in items.iter().enumerate() {
+                table.raw_set(i + 1, json_to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+        serde_json::Value::Object(fields) => {
+            let table = lua.create_table()?;
+            for (key, item) in fields {
+                table.raw_set(key.as_str(), json_to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+    })
+}
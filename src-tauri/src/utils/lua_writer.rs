@@ -0,0 +1,266 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{fs, ops::Range};
+use tauri::AppHandle;
+
+use super::history::save_file;
+use super::path_access::check_path;
+use super::lua_lexer::{tokenize, Token, TokenKind};
+use super::lua_parser::{fields, normalize_path};
+use super::vector::VECTOR_FUNCTIONS;
+
+/// A new value for the Lua value at `key_path`, e.g. `Config.Shops[2].price`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LuaEdit {
+    pub key_path: String,
+    pub value: Value,
+}
+
+pub fn is_identifier(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Formats a number so it keeps looking like a float when `float` is set, `12` becoming `12.0`.
+fn lua_number(n: f64, float: bool) -> String {
+    if float && n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{:.1}", n)
+    } else {
+        n.to_string()
+    }
+}
+
+fn lua_string(s: &str, quote: char) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+/// The components of a `{x, y, z, w}` object, in order, if it is one.
+pub fn vector_components(fields: &serde_json::Map<String, Value>) -> Option<Vec<f64>> {
+    let axes = ["x", "y", "z", "w"];
+    if fields.len() < 2 || fields.len() > 4 || !fields.keys().all(|key| axes.contains(&key.as_str())) {
+        return None;
+    }
+    axes[..fields.len()]
+        .iter()
+        .map(|axis| fields.get(*axis).and_then(Value::as_f64))
+        .collect()
+}
+
+/// The tokens of the field `key_path` inside `original`, when that is a table constructor.
+fn original_field<'a, 'src>(original: &'a [Token<'src>], key_path: &str) -> &'a [Token<'src>] {
+    if !original.first().is_some_and(|token| token.is_symbol("{")) {
+        return &[];
+    }
+    let key_path = normalize_path(key_path);
+    fields(original)
+        .into_iter()
+        .find(|field| field.value_end > field.value_start && normalize_path(&field.key_path) == key_path)
+        .map_or(&[], |field| &original[field.value_start..field.value_end])
+}
+
+fn lua_table(entries: Vec<String>) -> String {
+    if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", entries.join(", "))
+    }
+}
+
+/// Lua source for `value`. `original` is the value being replaced, used to keep its style:
+/// the same quotes, vectors staying vectors and plain tables staying tables, and floats
+/// staying floats.
+fn to_lua(value: &Value, original: &[Token]) -> String {
+    let first = original.first();
+    match value {
+        Value::Null => "nil".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => {
+                // Negative numbers lex as a `-` followed by the literal.
+                let literal = original.iter().find(|token| !token.is_symbol("-"));
+                if literal.is_some_and(|token| token.kind == TokenKind::Number && token.text.contains('.')) {
+                    lua_number(i as f64, true)
+                } else {
+                    i.to_string()
+                }
+            }
+            _ => lua_number(n.as_f64().unwrap_or_default(), true),
+        },
+        Value::String(s) => {
+            let quote = if first.is_some_and(|token| token.text.starts_with('\'')) { '\'' } else { '"' };
+            lua_string(s, quote)
+        }
+        Value::Object(entries) => {
+            // `{x, y}` objects come from both vector constructors and plain tables; only the
+            // former are written back as vectors.
+            let constructor = first.filter(|token| token.kind == TokenKind::Name && VECTOR_FUNCTIONS.contains(&token.text));
+            if let (Some(constructor), Some(components)) = (constructor, vector_components(entries)) {
+                let args: Vec<String> = components.iter().map(|c| lua_number(*c, true)).collect();
+                return format!("{}({})", constructor.text, args.join(", "));
+            }
+
+            lua_table(
+                entries
+                    .iter()
+                    .map(|(key, item)| {
+                        if is_identifier(key) {
+                            format!("{} = {}", key, to_lua(item, original_field(original, key)))
+                        } else if let Ok(index) = key.parse::<i64>() {
+                            let key_path = format!("[{}]", index);
+                            format!("{} = {}", key_path, to_lua(item, original_field(original, &key_path)))
+                        } else {
+                            let key_path = format!("[{}]", lua_string(key, '"'));
+                            format!("{} = {}", key_path, to_lua(item, original_field(original, &key_path)))
+                        }
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => lua_table(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| to_lua(item, original_field(original, &format!("[{}]", i + 1))))
+                .collect(),
+        ),
+    }
+}
+
+/// Applies `edits` to Lua source, replacing only the text of each edited value so comments,
+/// ordering and formatting elsewhere stay untouched. When a key path is assigned more than
+/// once, the last assignment is edited since that's the one that wins at runtime.
+pub fn apply_lua_edits(content: &str, edits: &[LuaEdit]) -> Result<String, String> {
+    let tokens: Vec<Token> = tokenize(content)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+    let fields = fields(&tokens);
+
+    let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+    for edit in edits {
+        let key_path = normalize_path(&edit.key_path);
+        let field = fields
+            .iter()
+            .rev()
+            .find(|field| field.value_end > field.value_start && normalize_path(&field.key_path) == key_path)
+            .ok_or_else(|| format!("{} not found", edit.key_path))?;
+
+        let original = &tokens[field.value_start..field.value_end];
+        let span = original[0].start..original[original.len() - 1].end();
+        if replacements.iter().any(|(other, _)| other.start < span.end && span.start < other.end) {
+            return Err(format!("{} overlaps another edit", edit.key_path));
+        }
+        replacements.push((span, to_lua(&edit.value, original)));
+    }
+
+    let mut rewritten = content.to_string();
+    replacements.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    for (span, text) in replacements {
+        rewritten.replace_range(span, &text);
+    }
+    Ok(rewritten)
+}
+
+/// Edits values in a Lua file by key path and returns the new source. With `dry_run` set the
+/// file is left as it is.
+#[tauri::command]
+pub fn set_lua_values(path: String, edits: Vec<LuaEdit>, dry_run: bool, app_handle: AppHandle) -> Result<String, String> {
+    println!("Writing {} Lua value(s) to: {:?}", edits.len(), path);
+    let file_path = &check_path(&app_handle, &path)?;
+    let content = fs::read_to_string(file_path).map_err(|e| format!("{}: {}", path, e))?;
+    let rewritten = apply_lua_edits(&content, &edits)?;
+    if !dry_run {
+        save_file(&app_handle, file_path, rewritten.as_bytes())?;
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn edit(content: &str, key_path: &str, value: Value) -> Result<String, String> {
+        apply_lua_edits(content, &[LuaEdit { key_path: key_path.to_string(), value }])
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let content = "-- shop settings\nConfig.price = 10 -- per item\n--[[ Config.price = 5 ]]\n";
+        assert_eq!(
+            edit(content, "Config.price", json!(20)).unwrap(),
+            "-- shop settings\nConfig.price = 20 -- per item\n--[[ Config.price = 5 ]]\n"
+        );
+    }
+
+    #[test]
+    fn keeps_quote_style() {
+        let content = "Config.label = 'Shop'\nConfig.title = \"Store\"\n";
+        let edits = [
+            LuaEdit { key_path: "Config.label".to_string(), value: json!("Bob's") },
+            LuaEdit { key_path: "Config.title".to_string(), value: json!("Mega \"Store\"") },
+        ];
+        assert_eq!(
+            apply_lua_edits(content, &edits).unwrap(),
+            "Config.label = 'Bob\\'s'\nConfig.title = \"Mega \\\"Store\\\"\"\n"
+        );
+    }
+
+    #[test]
+    fn writes_negative_numbers() {
+        let content = "Config.offset = -1.5\nConfig.count = 3\n";
+        let edits = [
+            LuaEdit { key_path: "Config.offset".to_string(), value: json!(-2) },
+            LuaEdit { key_path: "Config.count".to_string(), value: json!(-4) },
+        ];
+        assert_eq!(apply_lua_edits(content, &edits).unwrap(), "Config.offset = -2.0\nConfig.count = -4\n");
+    }
+
+    #[test]
+    fn edits_quoted_key_paths() {
+        let content = "Config = {\n    [\"my key\"] = { price = 5 },\n}\n";
+        assert_eq!(
+            edit(content, "Config[\"my key\"].price", json!(7)).unwrap(),
+            "Config = {\n    [\"my key\"] = { price = 7 },\n}\n"
+        );
+    }
+
+    #[test]
+    fn missing_key_is_an_error() {
+        let error = edit("Config.price = 10\n", "Config.missing", json!(1)).unwrap_err();
+        assert_eq!(error, "Config.missing not found");
+    }
+
+    #[test]
+    fn plain_tables_stay_tables() {
+        let content = "Config.size = { x = 1, y = 2.0 }\n";
+        assert_eq!(
+            edit(content, "Config.size", json!({ "x": 3, "y": 4 })).unwrap(),
+            "Config.size = { x = 3, y = 4.0 }\n"
+        );
+    }
+
+    #[test]
+    fn vectors_stay_vectors() {
+        let content = "Config.spawn = vector3(1.0, 2.0, 3.0)\n";
+        assert_eq!(
+            edit(content, "Config.spawn", json!({ "x": -1, "y": 2.5, "z": 3 })).unwrap(),
+            "Config.spawn = vector3(-1.0, 2.5, 3.0)\n"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::RwLock,
+};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use super::history::write_atomic;
+
+const ROOTS_FILE: &str = "allowed_roots.json";
+/// The frontend's settings store, holding folders picked before they had to be allowed.
+const SETTINGS_FILE: &str = "settings.json";
+const SETTINGS_FOLDERS: [&str; 3] = ["base_path", "game_path", "save_path"];
+
+/// Canonical folders the frontend may read and write files in, besides the app's own data.
+/// Loaded from `ROOTS_FILE` on first use, or seeded from the settings if that doesn't exist
+/// yet; after that only Rust adds to it, from a folder the user picked.
+static ALLOWED_ROOTS: Lazy<RwLock<Option<Vec<PathBuf>>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Debug, thiserror::Error)]
+pub enum PathAccessError {
+    #[error("Access denied: {0} is outside the allowed folders")]
+    Denied(String),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Lock acquisition failed: {0}")]
+    LockError(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Failed to save allowed folders: {0}")]
+    SaveError(String),
+}
+
+impl Serialize for PathAccessError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl From<PathAccessError> for String {
+    fn from(error: PathAccessError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Resolves `path` the way the OS would, following symlinks and `..`. Parts that don't exist
+/// yet, like a file about to be written, are appended to their nearest existing ancestor and
+/// may not contain `..` themselves.
+pub fn resolve(path: &Path) -> Result<PathBuf, PathAccessError> {
+    if !path.is_absolute() {
+        return Err(PathAccessError::InvalidPath(format!("{} is not absolute", path.display())));
+    }
+
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(canonical) => {
+                let mut resolved = canonical;
+                for component in missing.iter().rev() {
+                    resolved.push(component);
+                }
+                return Ok(resolved);
+            }
+            Err(_) if existing.components().next_back() == Some(Component::ParentDir) => {
+                return Err(PathAccessError::InvalidPath(path.display().to_string()));
+            }
+            Err(e) => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(e.into());
+                };
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+        }
+    }
+}
+
+fn roots_file(app_handle: &AppHandle) -> Result<PathBuf, PathAccessError> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| PathAccessError::SaveError(e.to_string()))?;
+    Ok(dir.join(ROOTS_FILE))
+}
+
+/// The allowed roots, read from disk the first time.
+fn allowed_roots(app_handle: &AppHandle) -> Result<Vec<PathBuf>, PathAccessError> {
+    if let Some(roots) = ALLOWED_ROOTS
+        .read()
+        .map_err(|e| PathAccessError::LockError(e.to_string()))?
+        .as_ref()
+    {
+        return Ok(roots.clone());
+    }
+
+    let file = roots_file(app_handle)?;
+    if !file.exists() {
+        let roots = roots_from_settings(app_handle);
+        save_roots(app_handle, roots.clone())?;
+        return Ok(roots);
+    }
+
+    let roots: Vec<PathBuf> = fs::read_to_string(file)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    *ALLOWED_ROOTS
+        .write()
+        .map_err(|e| PathAccessError::LockError(e.to_string()))? = Some(roots.clone());
+    Ok(roots)
+}
+
+/// The canonical form of a folder that may be allowed. Drive or filesystem roots are refused,
+/// since they would allow everything.
+fn allowable_root(folder: &Path) -> Result<PathBuf, PathAccessError> {
+    let root = fs::canonicalize(folder)?;
+    if !root.is_dir() || root.parent().is_none() {
+        return Err(PathAccessError::InvalidPath(format!("{} can't be allowed", folder.display())));
+    }
+    Ok(root)
+}
+
+/// The base, game and save paths already in the settings store. Used once, when there is no
+/// allow-list yet, so folders chosen before it existed keep working.
+fn roots_from_settings(app_handle: &AppHandle) -> Vec<PathBuf> {
+    let settings: serde_json::Value = app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(SETTINGS_FILE)).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let mut roots = Vec::new();
+    for key in SETTINGS_FOLDERS {
+        let Some(folder) = settings.get(key).and_then(serde_json::Value::as_str).filter(|folder| !folder.is_empty()) else {
+            continue;
+        };
+        match allowable_root(Path::new(folder)) {
+            Ok(root) if !roots.contains(&root) => roots.push(root),
+            Ok(_) => {}
+            Err(e) => println!("Not allowing {} from settings: {}", key, e),
+        }
+    }
+    roots
+}
+
+fn save_roots(app_handle: &AppHandle, roots: Vec<PathBuf>) -> Result<Vec<String>, PathAccessError> {
+    let file = roots_file(app_handle)?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(&roots).map_err(|e| PathAccessError::SaveError(e.to_string()))?;
+    write_atomic(&file, json.as_bytes()).map_err(PathAccessError::SaveError)?;
+    println!("Allowed roots: {:?}", roots);
+
+    let names = roots.iter().map(|root| root.to_string_lossy().to_string()).collect();
+    *ALLOWED_ROOTS
+        .write()
+        .map_err(|e| PathAccessError::LockError(e.to_string()))? = Some(roots);
+    Ok(names)
+}
+
+/// Resolves `path` and checks it is inside one of the allowed roots or the app's data or cache
+/// folder. Returns the resolved path, which is what should be read or written from then on.
+pub fn check_path(app_handle: &AppHandle, path: &str) -> Result<PathBuf, PathAccessError> {
+    let resolved = resolve(Path::new(path))?;
+    // The allow-list lives in the app's data folder, but only Rust may change it.
+    if resolve(&roots_file(app_handle)?).is_ok_and(|file| file == resolved) {
+        println!("Denied access to: {:?}", path);
+        return Err(PathAccessError::Denied(path.to_string()));
+    }
+
+    let roots = allowed_roots(app_handle)?;
+    let app_dirs = [app_handle.path().app_data_dir(), app_handle.path().app_cache_dir()];
+    let app_dirs = app_dirs
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|dir| fs::canonicalize(dir).ok());
+
+    if roots.into_iter().chain(app_dirs).any(|root| resolved.starts_with(root)) {
+        Ok(resolved)
+    } else {
+        println!("Denied access to: {:?}", path);
+        Err(PathAccessError::Denied(path.to_string()))
+    }
+}
+
+/// Resolves `relative`, a path the frontend gave relative to the already checked `base`, and
+/// checks it doesn't escape `base` through `..` or a symlink.
+pub fn check_relative(base: &Path, relative: &str) -> Result<PathBuf, PathAccessError> {
+    let relative_path = Path::new(relative);
+    if relative_path.is_absolute() {
+        return Err(PathAccessError::InvalidPath(format!("{} is not relative", relative)));
+    }
+    let resolved = resolve(&base.join(relative_path))?;
+    if resolved.starts_with(base) && resolved != base {
+        Ok(resolved)
+    } else {
+        println!("Denied access to: {:?} in {:?}", relative, base);
+        Err(PathAccessError::Denied(relative.to_string()))
+    }
+}
+
+/// Folders the frontend may open and save files in.
+#[tauri::command]
+pub fn get_allowed_roots(app_handle: AppHandle) -> Result<Vec<String>, PathAccessError> {
+    Ok(allowed_roots(&app_handle)?
+        .iter()
+        .map(|root| root.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Asks the user for a folder in a native dialog and allows it. Apart from the one-time import
+/// of existing settings, this is the only way to add a root, so a script in the webview can't
+/// widen access on its own. Returns the picked folder, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn pick_allowed_root(app_handle: AppHandle) -> Result<Option<String>, PathAccessError> {
+    let Some(picked) = app_handle.dialog().file().set_title("Allow folder").blocking_pick_folder() else {
+        return Ok(None);
+    };
+    let picked = picked.into_path().map_err(|e| PathAccessError::InvalidPath(e.to_string()))?;
+    let root = allowable_root(&picked)?;
+
+    let mut roots = allowed_roots(&app_handle)?;
+    if !roots.contains(&root) {
+        roots.push(root);
+        save_roots(&app_handle, roots)?;
+    }
+    Ok(Some(picked.to_string_lossy().to_string()))
+}
+
+/// Stops allowing `root`. Returns the roots that are left.
+#[tauri::command]
+pub fn remove_allowed_root(root: String, app_handle: AppHandle) -> Result<Vec<String>, PathAccessError> {
+    let removed = fs::canonicalize(&root).unwrap_or_else(|_| PathBuf::from(&root));
+    let roots = allowed_roots(&app_handle)?
+        .into_iter()
+        .filter(|allowed| allowed != &removed && allowed.to_string_lossy() != root)
+        .collect();
+    save_roots(&app_handle, roots)
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+
+use super::path_access::{check_path, check_relative, resolve};
+
+const QUARANTINE_DIR: &str = "quarantine";
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    /// Where the file was, relative to the base path.
+    pub original: String,
+    /// The copy that was kept in its place.
+    pub kept: String,
+    pub quarantined: String,
+}
+
+/// Record of one quarantine operation, stored next to the files it moved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineManifest {
+    pub id: String,
+    pub base_path: String,
+    pub created: u64,
+    pub entries: Vec<QuarantineEntry>,
+    /// Groups the policy couldn't pick a copy to keep for; nothing in them was moved.
+    #[serde(default)]
+    pub skipped: Vec<Vec<String>>,
+}
+
+fn quarantine_root(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(QUARANTINE_DIR))
+}
+
+/// Renames `from` to `to`, falling back to copy and delete when they are on different drives.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| format!("{}: {}", from.display(), e))?;
+    fs::remove_file(from).map_err(|e| format!("{}: {}", from.display(), e))
+}
+
+fn write_manifest(dir: &Path, manifest: &QuarantineManifest) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    fs::write(dir.join(MANIFEST_FILE), json).map_err(|e| e.to_string())
+}
+
+/// Moves every `(original, kept)` file out of `base_path` into a new quarantine folder and
+/// writes its manifest. The manifest is rewritten after each move, so an operation that
+/// fails halfway can still be restored.
+pub fn quarantine_files(
+    app_handle: &AppHandle,
+    base_path: &Path,
+    files: Vec<(String, String)>,
+    skipped: Vec<Vec<String>>,
+) -> Result<QuarantineManifest, String> {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let id = created.to_string();
+    let dir = quarantine_root(app_handle)?.join(&id);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut manifest = QuarantineManifest {
+        id,
+        base_path: base_path.to_string_lossy().to_string(),
+        created,
+        entries: Vec::new(),
+        skipped,
+    };
+    write_manifest(&dir, &manifest)?;
+
+    for (original, kept) in files {
+        let source = check_relative(base_path, &original)?;
+        let quarantined = dir.join(FILES_DIR).join(&original);
+        move_file(&source, &quarantined)?;
+        manifest.entries.push(QuarantineEntry {
+            original,
+            kept,
+            quarantined: quarantined.to_string_lossy().to_string(),
+        });
+        write_manifest(&dir, &manifest)?;
+    }
+
+    Ok(manifest)
+}
+
+#[tauri::command]
+pub fn list_quarantines(app_handle: AppHandle) -> Result<Vec<QuarantineManifest>, String> {
+    let root = quarantine_root(&app_handle)?;
+    let Ok(entries) = fs::read_dir(&root) else {
+        return Ok(Vec::new());
+    };
+
+    let mut manifests: Vec<QuarantineManifest> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| fs::read_to_string(entry.path().join(MANIFEST_FILE)).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    manifests.sort_by_key(|manifest| std::cmp::Reverse(manifest.created));
+    Ok(manifests)
+}
+
+/// Moves every file of a quarantine operation back to where it came from and removes the
+/// quarantine folder. Nothing is moved if any original location is taken again.
+#[tauri::command]
+pub fn restore_quarantine(id: String, app_handle: AppHandle) -> Result<Vec<String>, String> {
+    println!("Restoring quarantine: {:?}", id);
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid quarantine id: {}", id));
+    }
+    let dir = quarantine_root(&app_handle)?.join(&id);
+    let json = fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(|e| format!("{}: {}", id, e))?;
+    let manifest: QuarantineManifest = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+
+    // The manifest is only a file on disk, so check every path in it before moving anything.
+    let base_path = &check_path(&app_handle, &manifest.base_path)?;
+    let files_dir = resolve(&dir.join(FILES_DIR))?;
+    let mut moves = Vec::new();
+    for entry in &manifest.entries {
+        let original = check_relative(base_path, &entry.original)?;
+        let quarantined = resolve(Path::new(&entry.quarantined))?;
+        if !quarantined.starts_with(&files_dir) {
+            return Err(format!("{} is outside the quarantine folder", entry.quarantined));
+        }
+        if original.exists() {
+            return Err(format!("{} already exists", entry.original));
+        }
+        moves.push((quarantined, original));
+    }
+
+    let mut restored = Vec::new();
+    for (i, (entry, (quarantined, original))) in manifest.entries.iter().zip(&moves).enumerate() {
+        if let Err(e) = move_file(quarantined, original) {
+            // Keep only what is still quarantined so the restore can be retried.
+            let remaining = QuarantineManifest {
+                entries: manifest.entries[i..].to_vec(),
+                ..manifest.clone()
+            };
+            write_manifest(&dir, &remaining)?;
+            return Err(e);
+        }
+        restored.push(entry.original.clone());
+    }
+    fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    Ok(restored)
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+pub const MANIFEST_FILES: [&str; 2] = ["fxmanifest.lua", "__resource.lua"];
+pub const STREAM_DIR: &str = "stream";
+pub const STREAMED_EXTENSIONS: [&str; 8] = ["ytd", "ydr", "yft", "ymap", "ytyp", "ybn", "ycd", "ymf"];
+
+pub fn is_resource_root(dir: &Path) -> bool {
+    MANIFEST_FILES.iter().any(|manifest| dir.join(manifest).is_file())
+}
+
+/// Maps files to the FiveM resource they belong to: the closest parent folder holding an
+/// `fxmanifest.lua` or `__resource.lua`. Lookups are cached per folder.
+pub struct ResourceResolver {
+    base_path: PathBuf,
+    roots: HashMap<PathBuf, Option<PathBuf>>,
+}
+
+impl ResourceResolver {
+    pub fn new(base_path: &Path) -> Self {
+        Self {
+            base_path: base_path.to_path_buf(),
+            roots: HashMap::new(),
+        }
+    }
+
+    /// The resource root of `relative_file`, relative to the base path.
+    pub fn root_of(&mut self, relative_file: &str) -> Option<PathBuf> {
+        let dir = Path::new(relative_file).parent()?.to_path_buf();
+        self.root_of_dir(dir)
+    }
+
+    fn root_of_dir(&mut self, dir: PathBuf) -> Option<PathBuf> {
+        if let Some(root) = self.roots.get(&dir) {
+            return root.clone();
+        }
+
+        let root = if is_resource_root(&self.base_path.join(&dir)) {
+            Some(dir.clone())
+        } else {
+            dir.parent().and_then(|parent| self.root_of_dir(parent.to_path_buf()))
+        };
+        self.roots.insert(dir, root.clone());
+        root
+    }
+
+    /// The resource name (its folder name) of `relative_file`.
+    pub fn name_of(&mut self, relative_file: &str) -> Option<String> {
+        self.root_of(relative_file)?
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
+    /// Whether `relative_file` is an asset its resource streams: a game file somewhere under
+    /// the resource's `stream/` folder.
+    pub fn is_streamed(&mut self, relative_file: &str) -> bool {
+        let path = Path::new(relative_file);
+        let streamable = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| STREAMED_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if !streamable {
+            return false;
+        }
+
+        self.root_of(relative_file).is_some_and(|root| {
+            path.strip_prefix(&root)
+                .ok()
+                .and_then(|inner| inner.components().next())
+                .is_some_and(|first| first.as_os_str().eq_ignore_ascii_case(STREAM_DIR))
+        })
+    }
+}
+
+/// Resource names and `[category]` folders in the order `ensure`/`start` lines in a
+/// server.cfg start them. Later duplicates are dropped since the resource is already running.
+pub fn ensure_order(server_cfg: &str) -> Vec<String> {
+    let mut order: Vec<String> = Vec::new();
+    for line in server_cfg.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let line = line.split("//").next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        if !matches!(words.next(), Some("ensure" | "start")) {
+            continue;
+        }
+        if let Some(name) = words.next() {
+            if !order.iter().any(|started| started == name) {
+                order.push(name.to_string());
+            }
+        }
+    }
+    order
+}
+
+/// Where in `order` the resource at `root` gets started, either by its own name or through an
+/// ensured `[category]` folder it sits in.
+pub fn start_position(order: &[String], root: &Path) -> Option<usize> {
+    let name = root.file_name()?.to_str()?;
+    root.components()
+        .filter_map(|component| component.as_os_str().to_str())
+        .filter(|folder| *folder == name || (folder.starts_with('[') && folder.ends_with(']')))
+        .filter_map(|folder| order.iter().position(|started| started == folder))
+        .min()
+}
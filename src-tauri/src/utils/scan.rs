@@ -0,0 +1,106 @@
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// Project-level ignore file, read with the same rules as `.gitignore`.
+pub const IGNORE_FILE: &str = ".fivemdevignore";
+
+static SCAN_CONFIG: Lazy<RwLock<ScanConfig>> = Lazy::new(|| RwLock::new(ScanConfig::default()));
+
+/// Which files directory scans look at. Shared by every command that walks the base path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanConfig {
+    /// Gitignore-style globs a file has to match to be scanned. Empty means every file.
+    pub include: Vec<String>,
+    /// Gitignore-style globs for files and folders to skip.
+    pub exclude: Vec<String>,
+    /// Honour `.gitignore`, `.ignore` and `.fivemdevignore` files.
+    pub use_ignore_files: bool,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: vec!["node_modules/".to_string(), ".git/".to_string(), "\\[backup\\]/".to_string()],
+            use_ignore_files: true,
+            max_depth: None,
+            follow_symlinks: false,
+        }
+    }
+}
+
+impl ScanConfig {
+    fn overrides(&self, root: &Path) -> Result<ignore::overrides::Override, ignore::Error> {
+        let mut builder = OverrideBuilder::new(root);
+        for glob in &self.include {
+            builder.add(glob)?;
+        }
+        // Overrides treat `!` globs as ignores.
+        for glob in &self.exclude {
+            builder.add(&format!("!{}", glob))?;
+        }
+        builder.build()
+    }
+
+    /// Every file under `root` this config lets through.
+    pub fn files(&self, root: &Path) -> Vec<PathBuf> {
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .hidden(false)
+            .parents(self.use_ignore_files)
+            .ignore(self.use_ignore_files)
+            .git_ignore(self.use_ignore_files)
+            .git_exclude(self.use_ignore_files)
+            .git_global(false)
+            .require_git(false)
+            .max_depth(self.max_depth)
+            .follow_links(self.follow_symlinks);
+        if self.use_ignore_files {
+            builder.add_custom_ignore_filename(IGNORE_FILE);
+        }
+        match self.overrides(root) {
+            Ok(overrides) => {
+                builder.overrides(overrides);
+            }
+            Err(e) => println!("Ignoring invalid scan globs: {}", e),
+        }
+
+        builder
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+}
+
+/// The scan config currently set by the frontend.
+pub fn scan_config() -> ScanConfig {
+    SCAN_CONFIG.read().map(|config| config.clone()).unwrap_or_default()
+}
+
+/// Every file under `root` that the current scan config lets through.
+pub fn scan_files(root: &Path) -> Vec<PathBuf> {
+    scan_config().files(root)
+}
+
+#[tauri::command]
+pub fn get_scan_config() -> ScanConfig {
+    scan_config()
+}
+
+#[tauri::command]
+pub fn set_scan_config(config: ScanConfig) -> Result<(), String> {
+    println!("Setting scan config: {:?}", config);
+    config.overrides(Path::new("")).map_err(|e| e.to_string())?;
+    *SCAN_CONFIG.write().map_err(|e| e.to_string())? = config;
+    Ok(())
+}
@@ -1,34 +1,507 @@
 use once_cell::sync::Lazy;
-use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    path::Path,
-};
-use walkdir::WalkDir;
+use std::{fs, ops::Range, path::Path};
 
-#[derive(Debug, Clone, serde::Serialize)]
+use super::lua_lexer::{self, Token, TokenKind};
+use super::lua_parser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorSyntax {
+    /// `vector3(1.0, 2.0, 3.0)`, `vec(1.0, 2.0)`
+    VectorCall,
+    /// `{x = 1.0, y = 2.0, z = 3.0}`, also when assigned as `coords = { x = ..., w = ... }`
+    KeyedTable,
+    /// `coords = { 1.0, 2.0, 3.0 }`, only when the key looks like a coordinate
+    CoordArray,
+    /// `{"x": 1.0, "y": 2.0, "z": 3.0}`
+    JsonObject,
+    /// `new Vector3(1.0, 2.0, 3.0)` in JavaScript/TypeScript and C#
+    Constructor,
+    /// `[1.0, 2.0, 3.0]`
+    NumberArray,
+    /// `<position x="1.0" y="2.0" z="3.0" />` in CodeWalker XML exports
+    XmlAttributes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceType {
+    Lua,
+    Javascript,
+    CSharp,
+    Json,
+    YmapXml,
+}
+
+impl SourceType {
+    pub const ALL: [SourceType; 5] = [
+        SourceType::Lua,
+        SourceType::Javascript,
+        SourceType::CSharp,
+        SourceType::Json,
+        SourceType::YmapXml,
+    ];
+
+    pub fn of(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if file_name.ends_with(".ymap.xml") {
+            return Some(SourceType::YmapXml);
+        }
+
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "lua" => Some(SourceType::Lua),
+            "js" | "mjs" | "cjs" | "ts" => Some(SourceType::Javascript),
+            "cs" => Some(SourceType::CSharp),
+            "json" => Some(SourceType::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extractors(self) -> Vec<&'static dyn CoordExtractor> {
+        match self {
+            SourceType::Lua => vec![&LuaExtractor],
+            SourceType::Javascript => vec![&*JS_CONSTRUCTOR, &*NUMBER_ARRAY],
+            SourceType::CSharp => vec![&*CS_CONSTRUCTOR],
+            SourceType::Json => vec![&*JSON_OBJECT, &*NUMBER_ARRAY],
+            SourceType::YmapXml => vec![&*XML_POSITION],
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct VectorInfo {
     pub vector: Vec<f32>,
     pub file: String,
     pub line_number: usize,
+    pub column: usize,
+    pub syntax: VectorSyntax,
+    pub source: SourceType,
+    pub in_comment: bool,
+    /// Key path of the Lua table field or variable holding the vector, e.g. `Config.Shops[3].coords`.
+    pub key_path: Option<String>,
+    /// Innermost function call the vector is passed to, e.g. `AddBlipForCoord`.
+    pub call: Option<String>,
+    /// The source lines around the match, the matched line included.
+    pub context: Vec<String>,
 }
 
-static RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"\b(?:vector[234]?|vec[234]?)\(\s*(-?\d*\.?\d+)\s*(?:,\s*(-?\d*\.?\d+))?\s*(?:,\s*(-?\d*\.?\d+))?\s*(?:,\s*(-?\d*\.?\d+))?\s*\)").unwrap()
-});
+/// Which indexed vectors a query should consider.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct VectorFilter {
+    #[serde(default = "all_source_types")]
+    pub file_types: Vec<SourceType>,
+    #[serde(default = "default_true")]
+    pub include_comments: bool,
+}
+
+fn all_source_types() -> Vec<SourceType> {
+    SourceType::ALL.to_vec()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl VectorFilter {
+    pub fn new(file_types: Option<Vec<SourceType>>, include_comments: Option<bool>) -> Self {
+        Self {
+            file_types: file_types.unwrap_or_else(all_source_types),
+            include_comments: include_comments.unwrap_or(true),
+        }
+    }
+
+    pub fn accepts(&self, vec_info: &VectorInfo) -> bool {
+        self.file_types.contains(&vec_info.source) && (self.include_comments || !vec_info.in_comment)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VectorMatch {
+    #[serde(flatten)]
+    pub info: VectorInfo,
+    pub distance: f32,
+}
+
+/// Vectors from different files that sit within a tolerance of each other.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CoordinateCluster {
+    pub center: Vec<f32>,
+    pub files: Vec<String>,
+    pub resources: Vec<String>,
+    pub members: Vec<VectorInfo>,
+}
+
+/// A coordinate found in a file, located by the byte offset where its match starts.
+#[derive(Debug, Clone)]
+pub struct RawVector {
+    pub offset: usize,
+    pub vector: Vec<f32>,
+    /// Byte range of each component's number literal, sign included.
+    pub spans: Vec<Range<usize>>,
+    pub syntax: VectorSyntax,
+    pub in_comment: bool,
+    pub key_path: Option<String>,
+    pub call: Option<String>,
+}
+
+pub trait CoordExtractor: Send + Sync {
+    fn extract(&self, content: &str) -> Vec<RawVector>;
+}
+
+/// Extractor for any syntax that can be described by a regex with one capture group per component.
+pub struct RegexExtractor {
+    syntax: VectorSyntax,
+    re: Regex,
+}
+
+impl RegexExtractor {
+    pub fn new(syntax: VectorSyntax, pattern: &str) -> Self {
+        Self {
+            syntax,
+            re: Regex::new(&pattern.replace("{num}", NUM)).unwrap(),
+        }
+    }
+}
+
+impl CoordExtractor for RegexExtractor {
+    fn extract(&self, content: &str) -> Vec<RawVector> {
+        self.re
+            .captures_iter(content)
+            .filter_map(|caps| {
+                let (vector, spans): (Vec<f32>, Vec<Range<usize>>) = caps
+                    .iter()
+                    .skip(1) // Skip full match
+                    .flatten()
+                    .filter_map(|m| m.as_str().parse::<f32>().ok().map(|value| (value, m.range())))
+                    .unzip();
+
+                (2..=4).contains(&vector.len()).then(|| RawVector {
+                    offset: caps.get(0).map_or(0, |m| m.start()),
+                    vector,
+                    spans,
+                    syntax: self.syntax,
+                    in_comment: false,
+                    key_path: None,
+                    call: None,
+                })
+            })
+            .collect()
+    }
+}
+
+const CONTEXT_LINES: usize = 2;
+const NUM: &str = r"(-?\d*\.?\d+(?:[eE][-+]?\d+)?)";
+
+pub const VECTOR_FUNCTIONS: [&str; 8] = ["vector", "vector2", "vector3", "vector4", "vec", "vec2", "vec3", "vec4"];
+const HEADING_KEYS: [&str; 3] = ["w", "h", "heading"];
+
+/// Whole keys only, so `velocity` or `deposit` don't count as coordinates.
+static COORD_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?i:coords?|pos|position|location|loc|spawn|spawns?_?points?|points?)$").unwrap());
+
+/// A component value and the byte range of its literal.
+type Component = (f32, Range<usize>);
 
-pub fn parse_vector(s: &str) -> Option<Vec<f32>> {
-    RE.captures(s)
-        .map(|caps| {
-            caps.iter()
-                .skip(1) // Skip full match
-                .flatten()
-                .filter_map(|m| m.as_str().parse().ok())
-                .collect::<Vec<f32>>()
+/// Token-based extractor for Lua, so calls split over several lines are still found and
+/// matches inside comments can be told apart from live code.
+pub struct LuaExtractor;
+
+impl LuaExtractor {
+    /// A number literal with an optional unary minus, returning the value, its byte range and
+    /// the index after it.
+    fn number_at(tokens: &[Token], i: usize) -> Option<(Component, usize)> {
+        let (negative, start) = match tokens.get(i) {
+            Some(token) if token.is_symbol("-") => (true, i + 1),
+            _ => (false, i),
+        };
+        let token = tokens.get(start).filter(|token| token.kind == TokenKind::Number)?;
+        let value = lua_lexer::parse_number(token.text)? as f32;
+        let value = if negative { -value } else { value };
+        Some(((value, tokens[i].start..token.end()), start + 1))
+    }
+
+    fn is_name(tokens: &[Token], i: usize, names: &[&str]) -> bool {
+        tokens.get(i).is_some_and(|token| {
+            token.kind == TokenKind::Name && names.iter().any(|name| token.text.eq_ignore_ascii_case(name))
         })
-        .filter(|v| (2..=4).contains(&v.len()))
+    }
+
+    fn is_symbol(tokens: &[Token], i: usize, symbol: &str) -> bool {
+        tokens.get(i).is_some_and(|token| token.is_symbol(symbol))
+    }
+
+    /// `n, n, n` up to four numbers, stopping before the token that ends the list.
+    fn number_list(tokens: &[Token], mut i: usize) -> Option<(Vec<Component>, usize)> {
+        let mut vector = Vec::new();
+        loop {
+            let (value, next) = Self::number_at(tokens, i)?;
+            vector.push(value);
+            i = next;
+            if vector.len() == 4 || !Self::is_symbol(tokens, i, ",") || Self::number_at(tokens, i + 1).is_none() {
+                break;
+            }
+            i += 1;
+        }
+        Some((vector, i))
+    }
+
+    /// `vector3(1.0, 2.0, 3.0)` starting at the function name.
+    fn vector_call(tokens: &[Token], i: usize) -> Option<Vec<Component>> {
+        if !Self::is_name(tokens, i, &VECTOR_FUNCTIONS) || !Self::is_symbol(tokens, i + 1, "(") {
+            return None;
+        }
+        let (vector, end) = Self::number_list(tokens, i + 2)?;
+        Self::is_symbol(tokens, end, ")").then_some(vector)
+    }
+
+    /// `{ x = 1.0, y = 2.0, z = 3.0, w = 90.0 }` starting at the opening brace.
+    fn keyed_table(tokens: &[Token], i: usize) -> Option<Vec<Component>> {
+        if !Self::is_symbol(tokens, i, "{") {
+            return None;
+        }
+
+        let mut vector = Vec::new();
+        let mut i = i + 1;
+        for keys in [&["x"][..], &["y"], &["z"], &HEADING_KEYS] {
+            if !Self::is_name(tokens, i, keys) || !Self::is_symbol(tokens, i + 1, "=") {
+                break;
+            }
+            let (value, next) = Self::number_at(tokens, i + 2)?;
+            vector.push(value);
+            i = next;
+            if !Self::is_symbol(tokens, i, ",") {
+                break;
+            }
+            i += 1;
+        }
+
+        let closed = Self::is_symbol(tokens, i, "}") || Self::is_symbol(tokens, i.saturating_sub(1), ",");
+        (vector.len() >= 2 && closed).then_some(vector)
+    }
+
+    /// `coords = { 1.0, 2.0, 3.0 }` starting at the key, only for keys that look like coordinates.
+    fn coord_array(tokens: &[Token], i: usize) -> Option<Vec<Component>> {
+        let key = tokens.get(i).filter(|token| token.kind == TokenKind::Name)?;
+        if !COORD_KEY.is_match(key.text) || !Self::is_symbol(tokens, i + 1, "=") || !Self::is_symbol(tokens, i + 2, "{") {
+            return None;
+        }
+        let (vector, mut end) = Self::number_list(tokens, i + 3)?;
+        if Self::is_symbol(tokens, end, ",") {
+            end += 1;
+        }
+        Self::is_symbol(tokens, end, "}").then_some(vector)
+    }
+
+    fn scan(tokens: &[Token], in_comment: bool, out: &mut Vec<RawVector>) {
+        let found: Vec<(usize, Vec<Component>, VectorSyntax)> = (0..tokens.len())
+            .filter_map(|i| {
+                Self::vector_call(tokens, i)
+                    .map(|vector| (i, vector, VectorSyntax::VectorCall))
+                    .or_else(|| Self::keyed_table(tokens, i).map(|vector| (i, vector, VectorSyntax::KeyedTable)))
+                    .or_else(|| Self::coord_array(tokens, i).map(|vector| (i, vector, VectorSyntax::CoordArray)))
+            })
+            .filter(|(_, vector, _)| (2..=4).contains(&vector.len()))
+            .collect();
+        if found.is_empty() {
+            return;
+        }
+
+        let fields = lua_parser::fields(tokens);
+        let indexes: Vec<usize> = found.iter().map(|(i, _, _)| *i).collect();
+        let calls = lua_parser::enclosing_calls(tokens, &indexes, |name| {
+            VECTOR_FUNCTIONS.iter().any(|function| name.eq_ignore_ascii_case(function))
+        });
+
+        for ((i, components, syntax), call) in found.into_iter().zip(calls) {
+            // `coords = { 1, 2, 3 }` starts at the key, so look up the field from its value.
+            let value_token = if syntax == VectorSyntax::CoordArray { i + 2 } else { i };
+            let (vector, spans) = components.into_iter().unzip();
+            out.push(RawVector {
+                offset: tokens[i].start,
+                vector,
+                spans,
+                syntax,
+                in_comment,
+                key_path: lua_parser::field_at(&fields, value_token).map(|field| field.key_path.clone()),
+                call,
+            });
+        }
+    }
+}
+
+impl CoordExtractor for LuaExtractor {
+    fn extract(&self, content: &str) -> Vec<RawVector> {
+        let tokens = lua_lexer::tokenize(content);
+        let (comments, code): (Vec<Token>, Vec<Token>) =
+            tokens.into_iter().partition(|token| token.kind == TokenKind::Comment);
+
+        let mut found = Vec::new();
+        Self::scan(&code, false, &mut found);
+
+        for comment in comments {
+            let Some((body, body_start)) = comment.comment_body() else {
+                continue;
+            };
+            let mut in_comment = Vec::new();
+            Self::scan(&lua_lexer::tokenize(body), true, &mut in_comment);
+            found.extend(in_comment.into_iter().map(|raw| RawVector {
+                offset: raw.offset + body_start,
+                spans: raw
+                    .spans
+                    .iter()
+                    .map(|span| span.start + body_start..span.end + body_start)
+                    .collect(),
+                ..raw
+            }));
+        }
+
+        found
+    }
+}
+
+static JSON_OBJECT: Lazy<RegexExtractor> = Lazy::new(|| {
+    RegexExtractor::new(
+        VectorSyntax::JsonObject,
+        r#"(?i)\{\s*"x"\s*:\s*{num}\s*,\s*"y"\s*:\s*{num}\s*(?:,\s*"z"\s*:\s*{num}\s*)?(?:,\s*"(?:w|h|heading)"\s*:\s*{num}\s*)?[,}]"#,
+    )
+});
+
+static JS_CONSTRUCTOR: Lazy<RegexExtractor> = Lazy::new(|| {
+    RegexExtractor::new(
+        VectorSyntax::Constructor,
+        r"\bnew\s+(?:[\w.]+\.)?Vector[234]\(\s*{num}\s*,\s*{num}\s*(?:,\s*{num}\s*)?(?:,\s*{num}\s*)?\)",
+    )
+});
+
+static CS_CONSTRUCTOR: Lazy<RegexExtractor> = Lazy::new(|| {
+    RegexExtractor::new(
+        VectorSyntax::Constructor,
+        r"\bnew\s+(?:[\w.]+\.)?Vector[234]\(\s*{num}[fFdD]?\s*,\s*{num}[fFdD]?\s*(?:,\s*{num}[fFdD]?\s*)?(?:,\s*{num}[fFdD]?\s*)?\)",
+    )
+});
+
+static NUMBER_ARRAY: Lazy<RegexExtractor> = Lazy::new(|| {
+    RegexExtractor::new(
+        VectorSyntax::NumberArray,
+        r"\[\s*{num}\s*,\s*{num}\s*,\s*{num}\s*(?:,\s*{num}\s*)?\]",
+    )
+});
+
+static XML_POSITION: Lazy<RegexExtractor> = Lazy::new(|| {
+    RegexExtractor::new(
+        VectorSyntax::XmlAttributes,
+        r#"<position\s+x="{num}"\s+y="{num}"\s+z="{num}"\s*/>"#,
+    )
+});
+
+/// Shape a region query matches positions against.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VectorRegion {
+    Sphere {
+        center: Vec<f32>,
+        radius: f32,
+    },
+    /// Axis-aligned box; Z is only checked when both corners have one.
+    Box {
+        min: Vec<f32>,
+        max: Vec<f32>,
+    },
+    /// PolyZone-style 2D polygon with optional Z limits.
+    Polygon {
+        points: Vec<[f32; 2]>,
+        min_z: Option<f32>,
+        max_z: Option<f32>,
+    },
+}
+
+/// How the 4th component of a vector is treated by region queries.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum HeadingMode {
+    #[default]
+    Ignore,
+    /// Only match vectors that have a heading within `tolerance` degrees of `heading`, with wrap-around.
+    Compare { heading: f32, tolerance: f32 },
+}
+
+impl HeadingMode {
+    pub fn accepts(&self, v: &[f32]) -> bool {
+        match self {
+            HeadingMode::Ignore => true,
+            HeadingMode::Compare { heading, tolerance } => {
+                v.get(3).is_some_and(|h| heading_difference(*h, *heading) <= *tolerance)
+            }
+        }
+    }
+}
+
+/// Smallest angle between two headings in degrees, so 350 and 10 are 20 apart.
+pub fn heading_difference(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(360.0);
+    d.min(360.0 - d)
+}
+
+fn point_in_polygon(x: f32, y: f32, points: &[[f32; 2]]) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for (i, &[xi, yi]) in points.iter().enumerate() {
+        let [xj, yj] = points[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+impl VectorRegion {
+    pub fn contains(&self, v: &[f32]) -> bool {
+        if v.len() < 2 {
+            return false;
+        }
+        match self {
+            VectorRegion::Sphere { center, radius } => distance(position(center), position(v)) <= *radius,
+            VectorRegion::Box { min, max } => position(v)
+                .iter()
+                .zip(min.iter().zip(max))
+                .all(|(c, (lo, hi))| c >= &lo.min(*hi) && c <= &lo.max(*hi)),
+            VectorRegion::Polygon { points, min_z, max_z } => {
+                let z = v.get(2).copied();
+                let z_ok = match z {
+                    Some(z) => min_z.is_none_or(|min| z >= min) && max_z.is_none_or(|max| z <= max),
+                    None => true,
+                };
+                points.len() >= 3 && z_ok && point_in_polygon(v[0], v[1], points)
+            }
+        }
+    }
+
+    /// The (min_x, min_y, max_x, max_y) rectangle that holds the whole region.
+    pub fn bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        match self {
+            VectorRegion::Sphere { center, radius } if center.len() >= 2 => {
+                Some((center[0] - radius, center[1] - radius, center[0] + radius, center[1] + radius))
+            }
+            VectorRegion::Box { min, max } if min.len() >= 2 && max.len() >= 2 => Some((
+                min[0].min(max[0]),
+                min[1].min(max[1]),
+                min[0].max(max[0]),
+                min[1].max(max[1]),
+            )),
+            VectorRegion::Polygon { points, .. } if !points.is_empty() => Some(points.iter().fold(
+                (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                |(min_x, min_y, max_x, max_y), [x, y]| (min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y)),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The x, y and z components of a vector, without any heading.
+pub fn position(v: &[f32]) -> &[f32] {
+    &v[..v.len().min(3)]
 }
 
 pub fn distance(a: &[f32], b: &[f32]) -> f32 {
@@ -39,42 +512,161 @@ pub fn distance(a: &[f32], b: &[f32]) -> f32 {
         .sqrt()
 }
 
-pub fn extract_vectors_from_file(file_path: &Path, base_path: &Path) -> Vec<VectorInfo> {
-    let file = match File::open(file_path) {
-        Ok(file) => file,
-        Err(_) => return Vec::new(),
-    };
+/// Byte offset at which each line starts, for mapping match offsets back to line numbers.
+pub fn line_starts(content: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
 
-    let reader = BufReader::new(file);
-    reader
-        .lines()
-        .enumerate()
-        .par_bridge() // Parallel processing of lines
-        .filter_map(|(line_number, line)| {
-            line.ok().as_deref().and_then(|line| {
-                parse_vector(line).map(|vector| VectorInfo {
-                    vector,
-                    file: file_path
-                        .strip_prefix(base_path)
-                        .unwrap()
-                        .to_string_lossy()
-                        .to_string(),
-                    line_number,
-                })
-            })
+pub fn line_of(line_starts: &[usize], offset: usize) -> usize {
+    line_starts.partition_point(|&start| start <= offset) - 1
+}
+
+fn context_lines(content: &str, line_starts: &[usize], line_number: usize) -> Vec<String> {
+    let first = line_number.saturating_sub(CONTEXT_LINES);
+    let last = (line_number + CONTEXT_LINES + 1).min(line_starts.len());
+
+    (first..last)
+        .map(|line| {
+            let end = line_starts.get(line + 1).copied().unwrap_or(content.len());
+            content[line_starts[line]..end].trim_end().to_string()
         })
         .collect()
 }
 
-pub fn find_vectors_in_dir(dir: &Path) -> Vec<VectorInfo> {
-    println!("Searching for vectors in: {:?}", dir);
-    WalkDir::new(dir)
+/// Every coordinate in `content` in file order, with matches found by more than one extractor kept once.
+pub fn extract_raw_vectors(content: &str, source: SourceType) -> Vec<RawVector> {
+    let mut vectors: Vec<RawVector> = source
+        .extractors()
+        .iter()
+        .flat_map(|extractor| extractor.extract(content))
+        .collect();
+
+    vectors.sort_by_key(|raw| raw.offset);
+    vectors.dedup_by_key(|raw| raw.offset);
+    vectors
+}
+
+pub fn extract_vectors_from_file(file_path: &Path, base_path: &Path) -> Vec<VectorInfo> {
+    let Some(source) = SourceType::of(file_path) else {
+        return Vec::new();
+    };
+
+    let content = match fs::read_to_string(file_path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+
+    let file = file_path
+        .strip_prefix(base_path)
+        .unwrap_or(file_path)
+        .to_string_lossy()
+        .to_string();
+    let line_starts = line_starts(&content);
+
+    extract_raw_vectors(&content, source)
         .into_iter()
-        .par_bridge()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "lua")
+        .map(|raw| {
+            let line_number = line_of(&line_starts, raw.offset);
+            VectorInfo {
+                vector: raw.vector,
+                file: file.clone(),
+                line_number,
+                column: content[line_starts[line_number]..raw.offset].chars().count(),
+                syntax: raw.syntax,
+                source,
+                in_comment: raw.in_comment,
+                key_path: raw.key_path,
+                call: raw.call,
+                context: context_lines(&content, &line_starts, line_number),
+            }
         })
-        .flat_map(|entry| extract_vectors_from_file(entry.path(), dir).into_par_iter())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lua_vectors(content: &str) -> Vec<RawVector> {
+        extract_raw_vectors(content, SourceType::Lua)
+    }
+
+    #[test]
+    fn only_coordinate_keys_are_arrays() {
+        let content = "Config.coords = { 1.0, 2.0, 3.0 }\nConfig.SpawnPoint = { 4.0, 5.0 }\n\
+                       Config.Deposit = { 500, 1000 }\nConfig.velocity = { 1.0, 2.0 }\nConfig.purpose = { 1, 2 }\n";
+        let vectors: Vec<Vec<f32>> = lua_vectors(content).into_iter().map(|raw| raw.vector).collect();
+        assert_eq!(vectors, [vec![1.0, 2.0, 3.0], vec![4.0, 5.0]]);
+    }
+
+    /// The source text of each component literal.
+    fn literals<'a>(content: &'a str, raw: &RawVector) -> Vec<&'a str> {
+        raw.spans.iter().map(|span| &content[span.clone()]).collect()
+    }
+
+    #[test]
+    fn calls_can_span_lines() {
+        let content = "local spawn = vector3(\n    100.5,\n    -200.25,\n    30.0\n)\n";
+        let vectors = lua_vectors(content);
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors[0].vector, [100.5, -200.25, 30.0]);
+        assert_eq!(literals(content, &vectors[0]), ["100.5", "-200.25", "30.0"]);
+        assert_eq!(vectors[0].syntax, VectorSyntax::VectorCall);
+    }
+
+    #[test]
+    fn comments_and_long_strings() {
+        let content = r#"--[==[
+Config.old = vector3(1.0, 2.0, 3.0)
+]]still comment
+]==]
+local text = [[ vector3(4.0, 5.0, 6.0) ]]
+-- vector2(7.0, 8.0)
+Config.live = vector2(9.0, 10.0) -- trailing
+"#;
+        let vectors = lua_vectors(content);
+        let found: Vec<(Vec<&str>, bool)> = vectors.iter().map(|raw| (literals(content, raw), raw.in_comment)).collect();
+        assert_eq!(
+            found,
+            [(vec!["1.0", "2.0", "3.0"], true), (vec!["7.0", "8.0"], true), (vec!["9.0", "10.0"], false)]
+        );
+        assert_eq!(&content[vectors[0].offset..vectors[0].offset + 8], "vector3(");
+    }
+
+    #[test]
+    fn key_paths_and_calls() {
+        let content = r#"Config.Shops = {
+    { label = 'A', coords = vector3(1.0, 2.0, 3.0) },
+    [3] = { coords = { x = 4.0, y = 5.0 } },
+    ["Main St"] = { spawn = { 6.0, 7.0, 8.0 } },
+}
+AddBlip(vector2(1.0, 2.0))
+"#;
+        let found: Vec<(Option<String>, VectorSyntax, Option<String>)> =
+            lua_vectors(content).into_iter().map(|raw| (raw.key_path, raw.syntax, raw.call)).collect();
+        assert_eq!(
+            found,
+            [
+                (Some("Config.Shops[1].coords".to_string()), VectorSyntax::VectorCall, None),
+                (Some("Config.Shops[3].coords".to_string()), VectorSyntax::KeyedTable, None),
+                (Some("Config.Shops[\"Main St\"].spawn".to_string()), VectorSyntax::CoordArray, None),
+                (None, VectorSyntax::VectorCall, Some("AddBlip".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_and_columns() {
+        let dir = std::env::temp_dir().join("fivem_dev_tools_vector_test");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("client.lua");
+        fs::write(&file, "local a = 1\n--[[ é ]] local b = vector2(1.0, 2.0)\n").unwrap();
+
+        let vectors = extract_vectors_from_file(&file, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vectors.len(), 1);
+        assert_eq!((vectors[0].file.as_str(), vectors[0].line_number, vectors[0].column), ("client.lua", 1, 20));
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::history::write_atomic;
+use super::path_access::check_path;
+use super::vector::VectorInfo;
+
+const CSV_HEADER: [&str; 12] = [
+    "file", "line_number", "column", "x", "y", "z", "w", "syntax", "source", "in_comment", "key_path", "call",
+];
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    GeoJson,
+}
+
+/// Quotes a CSV field when it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The snake_case name serde gives an enum variant, e.g. `vector_call`.
+fn variant_name(value: impl serde::Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn to_csv(vectors: &[VectorInfo]) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push('\n');
+
+    for vec_info in vectors {
+        let component = |i: usize| vec_info.vector.get(i).map(f32::to_string).unwrap_or_default();
+        let row = [
+            vec_info.file.clone(),
+            vec_info.line_number.to_string(),
+            vec_info.column.to_string(),
+            component(0),
+            component(1),
+            component(2),
+            component(3),
+            variant_name(vec_info.syntax),
+            variant_name(vec_info.source),
+            vec_info.in_comment.to_string(),
+            vec_info.key_path.clone().unwrap_or_default(),
+            vec_info.call.clone().unwrap_or_default(),
+        ];
+        out.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// A FeatureCollection of points in GTA world coordinates, treated as a flat plane. Z and the
+/// heading are kept as properties so map tools that only read X/Y still place the points.
+fn to_geojson(vectors: &[VectorInfo]) -> Value {
+    let features: Vec<Value> = vectors
+        .iter()
+        .filter(|vec_info| vec_info.vector.len() >= 2)
+        .map(|vec_info| {
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [vec_info.vector[0], vec_info.vector[1]],
+                },
+                "properties": {
+                    "file": vec_info.file,
+                    "line_number": vec_info.line_number,
+                    "column": vec_info.column,
+                    "z": vec_info.vector.get(2),
+                    "heading": vec_info.vector.get(3),
+                    "syntax": vec_info.syntax,
+                    "source": vec_info.source,
+                    "in_comment": vec_info.in_comment,
+                    "key_path": vec_info.key_path,
+                    "call": vec_info.call,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+pub fn export_vectors_to(vectors: &[VectorInfo], format: ExportFormat, output_path: &Path) -> Result<(), String> {
+    let content = match format {
+        ExportFormat::Csv => to_csv(vectors),
+        ExportFormat::Json => serde_json::to_string_pretty(vectors).map_err(|e| e.to_string())?,
+        ExportFormat::GeoJson => serde_json::to_string_pretty(&to_geojson(vectors)).map_err(|e| e.to_string())?,
+    };
+    write_atomic(output_path, content.as_bytes())
+}
+
+#[tauri::command]
+pub fn export_vectors(
+    vectors: Vec<VectorInfo>,
+    format: ExportFormat,
+    output_path: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    println!("Exporting {} vectors as {:?} to: {:?}", vectors.len(), format, output_path);
+    export_vectors_to(&vectors, format, &check_path(&app_handle, &output_path)?)
+}
@@ -0,0 +1,429 @@
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+use tauri::{AppHandle, Manager};
+
+use super::scan::scan_files;
+use super::vector::{
+    self, extract_vectors_from_file, HeadingMode, SourceType, VectorFilter, VectorInfo, VectorMatch, VectorRegion,
+};
+
+// Bump whenever the on-disk layout or the parser output changes so stale indexes get rebuilt.
+const INDEX_VERSION: u32 = 5;
+const CELL_SIZE: f32 = 64.0;
+const INDEX_DIR: &str = "vector_index";
+
+static INDEXES: Lazy<Mutex<HashMap<PathBuf, VectorIndex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileEntry {
+    modified: u64,
+    size: u64,
+    vectors: Vec<VectorInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VectorIndex {
+    version: u32,
+    files: HashMap<String, FileEntry>,
+    #[serde(skip)]
+    grid: HashMap<(i32, i32), Vec<(String, usize)>>,
+    #[serde(skip)]
+    bounds: Option<((i32, i32), (i32, i32))>,
+}
+
+fn cell_of(value: f32) -> i32 {
+    (value / CELL_SIZE).floor() as i32
+}
+
+/// The cells of the square ring `ring` cells out from `center` that lie within `bounds`: the
+/// top and bottom rows over `xs`, and the left and right columns over `ys` between them.
+struct Ring {
+    rows: Vec<i64>,
+    xs: RangeInclusive<i64>,
+    columns: Vec<i64>,
+    ys: RangeInclusive<i64>,
+}
+
+impl Ring {
+    fn new((cx, cy): (i64, i64), ring: i64, ((min_x, min_y), (max_x, max_y)): ((i64, i64), (i64, i64))) -> Self {
+        let mut rows: Vec<i64> = vec![cy - ring, cy + ring];
+        let mut columns: Vec<i64> = vec![cx - ring, cx + ring];
+        rows.dedup();
+        columns.dedup();
+        rows.retain(|y| (min_y..=max_y).contains(y));
+        columns.retain(|x| (min_x..=max_x).contains(x));
+        Self {
+            rows,
+            xs: (cx - ring).max(min_x)..=(cx + ring).min(max_x),
+            columns,
+            ys: (cy - ring + 1).max(min_y)..=(cy + ring - 1).min(max_y),
+        }
+    }
+
+    fn len(&self) -> i64 {
+        let span = |range: &RangeInclusive<i64>| (range.end() - range.start() + 1).max(0);
+        self.rows.len() as i64 * span(&self.xs) + self.columns.len() as i64 * span(&self.ys)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        let rows = self.rows.iter().flat_map(|&y| self.xs.clone().map(move |x| (x, y)));
+        let columns = self.columns.iter().flat_map(|&x| self.ys.clone().map(move |y| (x, y)));
+        // Both ranges lie within the grid bounds, which are i32.
+        rows.chain(columns).map(|(x, y)| (x as i32, y as i32))
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some((modified, metadata.len()))
+}
+
+impl VectorIndex {
+    fn new() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            files: HashMap::new(),
+            grid: HashMap::new(),
+            bounds: None,
+        }
+    }
+
+    pub fn load(cache_file: &Path) -> Self {
+        let index = fs::read(cache_file)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<VectorIndex>(&bytes).ok())
+            .filter(|index| index.version == INDEX_VERSION);
+
+        match index {
+            Some(mut index) => {
+                index.rebuild_grid();
+                index
+            }
+            None => Self::new(),
+        }
+    }
+
+    pub fn save(&self, cache_file: &Path) -> std::io::Result<()> {
+        if let Some(parent) = cache_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_file = cache_file.with_extension("tmp");
+        fs::write(&tmp_file, serde_json::to_vec(self)?)?;
+        fs::rename(tmp_file, cache_file)
+    }
+
+    /// Re-parses only files whose mtime or size changed since the last refresh and drops
+    /// entries for files that no longer exist. Returns true if anything changed.
+    pub fn refresh(&mut self, base_path: &Path) -> bool {
+        let on_disk: Vec<(String, PathBuf, (u64, u64))> = scan_files(base_path)
+            .into_par_iter()
+            .filter(|path| SourceType::of(path).is_some())
+            .filter_map(|path| {
+                let relative = path.strip_prefix(base_path).ok()?.to_string_lossy().to_string();
+                let stamp = file_stamp(&path)?;
+                Some((relative, path, stamp))
+            })
+            .collect();
+
+        let before = self.files.len();
+        let seen: HashSet<&str> = on_disk.iter().map(|(relative, _, _)| relative.as_str()).collect();
+        self.files.retain(|relative, _| seen.contains(relative.as_str()));
+        let mut changed = self.files.len() != before;
+
+        let updated: Vec<(String, FileEntry)> = on_disk
+            .into_par_iter()
+            .filter(|(relative, _, (modified, size))| {
+                self.files
+                    .get(relative)
+                    .is_none_or(|entry| entry.modified != *modified || entry.size != *size)
+            })
+            .map(|(relative, path, (modified, size))| {
+                let vectors = extract_vectors_from_file(&path, base_path);
+                (relative, FileEntry { modified, size, vectors })
+            })
+            .collect();
+
+        if !updated.is_empty() {
+            println!("Re-indexed {} changed file(s)", updated.len());
+            changed = true;
+            self.files.extend(updated);
+        }
+
+        if changed {
+            self.rebuild_grid();
+        }
+        changed
+    }
+
+    fn rebuild_grid(&mut self) {
+        self.grid.clear();
+        for (relative, entry) in &self.files {
+            for (i, vec_info) in entry.vectors.iter().enumerate() {
+                // Literals like `1e39` overflow f32 and have no place on the grid.
+                if !vector::position(&vec_info.vector).iter().all(|c| c.is_finite()) {
+                    continue;
+                }
+                let key = (cell_of(vec_info.vector[0]), cell_of(vec_info.vector[1]));
+                self.grid.entry(key).or_default().push((relative.clone(), i));
+            }
+        }
+
+        self.bounds = self.grid.keys().fold(None, |bounds, &(x, y)| match bounds {
+            None => Some(((x, y), (x, y))),
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                Some(((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y))))
+            }
+        });
+    }
+
+    fn cell_distances(
+        &self,
+        v: &[f32],
+        key: (i32, i32),
+        filter: &VectorFilter,
+        out: &mut Vec<(f32, (String, usize))>,
+    ) {
+        let Some(cell) = self.grid.get(&key) else {
+            return;
+        };
+        for (relative, i) in cell {
+            if let Some(vec_info) = self.get(relative, *i).filter(|vec_info| filter.accepts(vec_info)) {
+                let d = vector::distance(vector::position(v), vector::position(&vec_info.vector));
+                out.push((d, (relative.clone(), *i)));
+            }
+        }
+    }
+
+    fn get(&self, relative: &str, i: usize) -> Option<&VectorInfo> {
+        self.files.get(relative).and_then(|entry| entry.vectors.get(i))
+    }
+
+    /// All indexed vectors accepted by `filter` within `dist` of `v`, paired with their distance.
+    pub fn within_distance(&self, v: &[f32], dist: f32, filter: &VectorFilter) -> Vec<(f32, VectorInfo)> {
+        if v.len() < 2 {
+            return Vec::new();
+        }
+        let Some(((grid_min_x, grid_min_y), (grid_max_x, grid_max_y))) = self.bounds else {
+            return Vec::new();
+        };
+
+        // Clamped to the occupied cells so a huge `dist` doesn't walk millions of empty ones.
+        let (min_x, max_x) = (cell_of(v[0] - dist).max(grid_min_x), cell_of(v[0] + dist).min(grid_max_x));
+        let (min_y, max_y) = (cell_of(v[1] - dist).max(grid_min_y), cell_of(v[1] + dist).min(grid_max_y));
+
+        let mut candidates = Vec::new();
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                self.cell_distances(v, (x, y), filter, &mut candidates);
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter(|(d, _)| *d <= dist)
+            .filter_map(|(d, (relative, i))| self.get(&relative, i).map(|vec_info| (d, vec_info.clone())))
+            .collect()
+    }
+
+    /// All indexed vectors accepted by `filter` and `heading` that lie inside `region`.
+    pub fn within_region(&self, region: &VectorRegion, heading: &HeadingMode, filter: &VectorFilter) -> Vec<VectorInfo> {
+        let Some((min_x, min_y, max_x, max_y)) = region.bounds() else {
+            return Vec::new();
+        };
+        let Some(((grid_min_x, grid_min_y), (grid_max_x, grid_max_y))) = self.bounds else {
+            return Vec::new();
+        };
+
+        let mut results = Vec::new();
+        for x in cell_of(min_x).max(grid_min_x)..=cell_of(max_x).min(grid_max_x) {
+            for y in cell_of(min_y).max(grid_min_y)..=cell_of(max_y).min(grid_max_y) {
+                let Some(cell) = self.grid.get(&(x, y)) else {
+                    continue;
+                };
+                results.extend(
+                    cell.iter()
+                        .filter_map(|(relative, i)| self.get(relative, *i))
+                        .filter(|vec_info| {
+                            filter.accepts(vec_info) && heading.accepts(&vec_info.vector) && region.contains(&vec_info.vector)
+                        })
+                        .cloned(),
+                );
+            }
+        }
+        results
+    }
+
+    /// Groups vectors accepted by `filter` whose positions are within `tolerance` of another
+    /// member, keeping only groups that span more than one file. Largest groups come first.
+    pub fn clusters(&self, tolerance: f32, filter: &VectorFilter) -> Vec<Vec<VectorInfo>> {
+        let points: Vec<&VectorInfo> = self
+            .files
+            .values()
+            .flat_map(|entry| &entry.vectors)
+            .filter(|vec_info| filter.accepts(vec_info))
+            .collect();
+
+        // A grid with tolerance-sized cells means every neighbour is in the surrounding 3x3 cells.
+        let cell_size = tolerance.max(0.01);
+        let key_of = |v: &[f32]| ((v[0] / cell_size).floor() as i64, (v[1] / cell_size).floor() as i64);
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, vec_info) in points.iter().enumerate() {
+            grid.entry(key_of(&vec_info.vector)).or_default().push(i);
+        }
+
+        let mut parents: Vec<usize> = (0..points.len()).collect();
+        fn find(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+
+        for (i, vec_info) in points.iter().enumerate() {
+            let (x, y) = key_of(&vec_info.vector);
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let Some(cell) = grid.get(&(x + dx, y + dy)) else {
+                        continue;
+                    };
+                    for &j in cell.iter().filter(|&&j| j > i) {
+                        let d = vector::distance(vector::position(&vec_info.vector), vector::position(&points[j].vector));
+                        if d <= tolerance {
+                            let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+                            parents[a] = b;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<VectorInfo>> = HashMap::new();
+        for (i, vec_info) in points.iter().enumerate() {
+            groups.entry(find(&mut parents, i)).or_default().push((*vec_info).clone());
+        }
+
+        let mut clusters: Vec<Vec<VectorInfo>> = groups
+            .into_values()
+            .filter(|members| members.iter().any(|member| member.file != members[0].file))
+            .collect();
+        for members in &mut clusters {
+            members.sort_by(|a, b| a.file.cmp(&b.file).then(a.line_number.cmp(&b.line_number)));
+        }
+        clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].file.cmp(&b[0].file)));
+        clusters
+    }
+
+    /// The `k` indexed vectors accepted by `filter` closest to `v`, nearest first.
+    ///
+    /// Walks the grid in square rings around the query cell, starting with the first ring that
+    /// reaches the grid, and stops once the k-th best distance is closer than anything an
+    /// unvisited ring could contain. When the rings left would cover more cells than the grid
+    /// holds, as with a query far from the data or one outlier stretching the bounds, every
+    /// occupied cell is checked instead.
+    pub fn nearest(&self, v: &[f32], k: usize, filter: &VectorFilter) -> Vec<VectorMatch> {
+        let Some(((min_x, min_y), (max_x, max_y))) = self.bounds else {
+            return Vec::new();
+        };
+        if v.len() < 2 || k == 0 || !v[0].is_finite() || !v[1].is_finite() {
+            return Vec::new();
+        }
+
+        // In i64 so rings around a query near the edge of the i32 cells can't overflow.
+        let center = (i64::from(cell_of(v[0])), i64::from(cell_of(v[1])));
+        let bounds = ((i64::from(min_x), i64::from(min_y)), (i64::from(max_x), i64::from(max_y)));
+        let (cx, cy) = center;
+        let ((min_x, min_y), (max_x, max_y)) = bounds;
+        let first_ring = [min_x - cx, cx - max_x, min_y - cy, cy - max_y].into_iter().max().unwrap_or(0).max(0);
+        let last_ring = [cx - min_x, max_x - cx, cy - min_y, max_y - cy].into_iter().max().unwrap_or(0).max(0);
+
+        let mut candidates = Vec::new();
+        let mut visited = 0;
+        for ring in first_ring..=last_ring {
+            let cells = Ring::new(center, ring, bounds);
+            visited += cells.len();
+            if visited > self.grid.len() as i64 {
+                candidates.clear();
+                for key in self.grid.keys() {
+                    self.cell_distances(v, *key, filter, &mut candidates);
+                }
+                break;
+            }
+            for key in cells.cells() {
+                self.cell_distances(v, key, filter, &mut candidates);
+            }
+
+            if candidates.len() >= k {
+                candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+                candidates.truncate(k);
+                // Anything in the next ring is at least `ring` whole cells away on x or y.
+                if candidates[k - 1].0 <= ring as f32 * CELL_SIZE {
+                    break;
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        candidates
+            .into_iter()
+            .take(k)
+            .filter_map(|(distance, (relative, i))| {
+                self.get(&relative, i).map(|info| VectorMatch {
+                    info: info.clone(),
+                    distance,
+                })
+            })
+            .collect()
+    }
+}
+
+fn cache_file_for(app_handle: &AppHandle, base_path: &Path) -> Option<PathBuf> {
+    // A stable hash, so the cache is found again after the app is rebuilt.
+    let hash = Sha256::digest(base_path.to_string_lossy().as_bytes());
+    let name: String = hash[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    app_handle
+        .path()
+        .app_cache_dir()
+        .ok()
+        .map(|dir| dir.join(INDEX_DIR).join(format!("{}.json", name)))
+}
+
+/// Brings the index for `base_path` up to date (loading it from disk on first use) and runs `f` against it.
+pub fn with_index<R>(app_handle: &AppHandle, base_path: &Path, f: impl FnOnce(&VectorIndex) -> R) -> R {
+    let cache_file = cache_file_for(app_handle, base_path);
+    let mut indexes = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+
+    let index = indexes.entry(base_path.to_path_buf()).or_insert_with(|| {
+        cache_file
+            .as_deref()
+            .map(VectorIndex::load)
+            .unwrap_or_else(VectorIndex::new)
+    });
+
+    if index.refresh(base_path) {
+        if let Some(cache_file) = &cache_file {
+            if let Err(e) = index.save(cache_file) {
+                println!("Failed to save vector index: {}", e);
+            }
+        }
+    }
+
+    f(index)
+}
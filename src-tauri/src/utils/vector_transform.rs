@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::Path,
+};
+use tauri::AppHandle;
+
+use super::history::save_file;
+use super::path_access::check_path;
+use super::vector::{extract_raw_vectors, HeadingMode, RawVector, SourceType, VectorFilter, VectorRegion};
+use super::vector_index::with_index;
+
+/// Decimals a rewritten number may get beyond what its vector was written with, so an offset
+/// like `0.25` still shows up on a whole-number coordinate.
+const MIN_PRECISION: usize = 4;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VectorTransform {
+    /// Adds `offset` component-wise; a 4th offset component shifts the heading.
+    Translate { offset: Vec<f64> },
+    /// Rotates around the Z axis through `pivot` and turns the heading by the same angle.
+    Rotate { pivot: Vec<f64>, degrees: f64 },
+    /// Overwrites the components given in `vector`, leaving any others untouched.
+    Replace { vector: Vec<f64> },
+}
+
+impl VectorTransform {
+    /// Works in `f64` on the values parsed from the file; the index's `f32` copies would lose
+    /// digits on map-sized coordinates.
+    pub fn apply(&self, v: &[f64]) -> Vec<f64> {
+        let mut out = v.to_vec();
+        match self {
+            VectorTransform::Translate { offset } => {
+                out.iter_mut().zip(offset).for_each(|(c, o)| *c += o);
+            }
+            VectorTransform::Rotate { pivot, degrees } => {
+                let (px, py) = (pivot.first().copied().unwrap_or(0.0), pivot.get(1).copied().unwrap_or(0.0));
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                let (dx, dy) = (v[0] - px, v[1] - py);
+                out[0] = px + dx * cos - dy * sin;
+                out[1] = py + dx * sin + dy * cos;
+                if let Some(heading) = out.get_mut(3) {
+                    *heading = (*heading + degrees).rem_euclid(360.0);
+                }
+            }
+            VectorTransform::Replace { vector } => {
+                out.iter_mut().zip(vector).for_each(|(c, r)| *c = *r);
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub line_number: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRewrite {
+    pub file: String,
+    pub changes: usize,
+    pub diff: Vec<DiffLine>,
+    pub backup: Option<String>,
+}
+
+fn decimals_of(literal: &str) -> usize {
+    literal
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.chars().take_while(char::is_ascii_digit).count())
+}
+
+fn round_to(value: f64, decimals: usize) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    (value * scale).round() / scale
+}
+
+/// Formats `value` rounded to `precision` decimals, with at least as many decimals as `original`
+/// had so `12.50` stays two-decimal. More decimals are only added when the value needs them.
+fn format_like(original: &str, value: f64, precision: usize) -> String {
+    let original_decimals = decimals_of(original);
+    let precision = precision.max(original_decimals);
+    let rounded = round_to(value, precision);
+    let decimals = (original_decimals..precision)
+        .find(|&decimals| round_to(rounded, decimals) == rounded)
+        .unwrap_or(precision);
+
+    let formatted = format!("{:.*}", decimals, rounded);
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => unsigned.to_string(),
+        _ => formatted,
+    }
+}
+
+/// Applies `transform` to every selected vector in `content`, replacing only the number literals.
+/// Components are re-parsed from the literals so the values keep every digit that was written.
+fn rewrite_content(
+    content: &str,
+    vectors: &[RawVector],
+    transform: &VectorTransform,
+) -> (String, usize) {
+    let mut edits = Vec::new();
+    let mut changes = 0;
+
+    for raw in vectors {
+        let literals: Vec<&str> = raw.spans.iter().map(|span| &content[span.clone()]).collect();
+        let values: Vec<f64> = literals
+            .iter()
+            .zip(&raw.vector)
+            .map(|(literal, parsed)| literal.replace(' ', "").parse().unwrap_or(*parsed as f64))
+            .collect();
+        // Rotation mixes components, so every component may need the most precise one's decimals.
+        let precision = literals.iter().map(|literal| decimals_of(literal)).max().unwrap_or(0).max(MIN_PRECISION);
+
+        let transformed = transform.apply(&values);
+        let before = edits.len();
+        for (((span, literal), old), new) in raw.spans.iter().zip(&literals).zip(&values).zip(&transformed) {
+            if old == new {
+                continue;
+            }
+            let text = format_like(literal, *new, precision);
+            if text != *literal {
+                edits.push((span.clone(), text));
+            }
+        }
+        if edits.len() > before {
+            changes += 1;
+        }
+    }
+
+    let mut rewritten = content.to_string();
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    for (span, text) in edits {
+        rewritten.replace_range(span, &text);
+    }
+    (rewritten, changes)
+}
+
+/// Line-by-line diff of two texts with the same number of lines, which holds because only
+/// number literals are ever replaced.
+fn line_diff(before: &str, after: &str) -> Vec<DiffLine> {
+    before
+        .lines()
+        .zip(after.lines())
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(line_number, (a, b))| DiffLine {
+            line_number,
+            before: a.to_string(),
+            after: b.to_string(),
+        })
+        .collect()
+}
+
+/// Rewrites every coordinate inside `region` under `base_path`. With `dry_run` set nothing is
+/// written and the returned diffs are only a preview.
+pub fn transform_vectors_in_dir(
+    app_handle: &AppHandle,
+    base_path: &Path,
+    region: &VectorRegion,
+    heading: &HeadingMode,
+    filter: &VectorFilter,
+    transform: &VectorTransform,
+    dry_run: bool,
+) -> Result<Vec<FileRewrite>, String> {
+    let files: BTreeSet<String> = with_index(app_handle, base_path, |index| index.within_region(region, heading, filter))
+        .into_iter()
+        .map(|vec_info| vec_info.file)
+        .collect();
+
+    let mut rewrites = Vec::new();
+    for file in files {
+        let file_path = base_path.join(&file);
+        let Some(source) = SourceType::of(&file_path) else {
+            continue;
+        };
+        let content = fs::read_to_string(&file_path).map_err(|e| format!("{}: {}", file, e))?;
+
+        // Re-extract instead of trusting the index so the spans match what is on disk right now.
+        let selected: Vec<RawVector> = extract_raw_vectors(&content, source)
+            .into_iter()
+            .filter(|raw| filter.include_comments || !raw.in_comment)
+            .filter(|raw| heading.accepts(&raw.vector) && region.contains(&raw.vector))
+            .collect();
+
+        let (rewritten, changes) = rewrite_content(&content, &selected, transform);
+        if changes == 0 {
+            continue;
+        }
+
+        let backup = if dry_run {
+            None
+        } else {
+            save_file(app_handle, &file_path, rewritten.as_bytes())?.map(|entry| entry.backup)
+        };
+
+        rewrites.push(FileRewrite {
+            file,
+            changes,
+            diff: line_diff(&content, &rewritten),
+            backup,
+        });
+    }
+
+    Ok(rewrites)
+}
+
+#[tauri::command]
+pub fn transform_vectors(
+    path: String,
+    region: VectorRegion,
+    heading: Option<HeadingMode>,
+    transform: VectorTransform,
+    dry_run: bool,
+    filter: Option<VectorFilter>,
+    app_handle: AppHandle,
+) -> Result<Vec<FileRewrite>, String> {
+    println!("Transforming vectors in {:?} in: {:?}", region, path);
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Commented-out coordinates are left alone unless asked for explicitly.
+    let filter = filter.unwrap_or_else(|| VectorFilter::new(None, Some(false)));
+    let heading = heading.unwrap_or_default();
+    let base_path = check_path(&app_handle, &path)?;
+    transform_vectors_in_dir(&app_handle, &base_path, &region, &heading, &filter, &transform, dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(content: &str, transform: VectorTransform) -> (String, usize) {
+        let vectors = extract_raw_vectors(content, SourceType::Lua);
+        rewrite_content(content, &vectors, &transform)
+    }
+
+    fn rotate(degrees: f64) -> VectorTransform {
+        VectorTransform::Rotate { pivot: vec![0.0, 0.0], degrees }
+    }
+
+    #[test]
+    fn format_keeps_original_decimals() {
+        assert_eq!(format_like("12.50", 13.5, MIN_PRECISION), "13.50");
+        assert_eq!(format_like("5", 6.0, MIN_PRECISION), "6");
+        assert_eq!(format_like("1.5", 1.75, MIN_PRECISION), "1.75");
+        assert_eq!(format_like("10", 10.123456789, MIN_PRECISION), "10.1235");
+    }
+
+    #[test]
+    fn format_rounds_to_precision() {
+        assert_eq!(format_like("1234.567891", 1244.567891000001, 6), "1244.567891");
+        assert_eq!(format_like("4000.1", 2.4e-13, MIN_PRECISION), "0.0");
+        assert_eq!(format_like("0.0", -2.4e-13, MIN_PRECISION), "0.0");
+        assert_eq!(format_like("1.0", 1234.567891, 6), "1234.567891");
+        assert_eq!(format_like("-3.25", -3.2500000001, MIN_PRECISION), "-3.25");
+    }
+
+    #[test]
+    fn translate_keeps_every_digit() {
+        let translate = VectorTransform::Translate { offset: vec![10.0, 0.0, 0.0] };
+        let (rewritten, changes) = rewrite("local a = vector3(1234.567891, -500.25, 30.0)", translate);
+        assert_eq!(rewritten, "local a = vector3(1244.567891, -500.25, 30.0)");
+        assert_eq!(changes, 1);
+    }
+
+    #[test]
+    fn rotate_lands_on_exact_values() {
+        let (rewritten, _) = rewrite("vector3(4000.1, 0.0, 5.0)", rotate(90.0));
+        assert_eq!(rewritten, "vector3(0.0, 4000.1, 5.0)");
+
+        let (rewritten, _) = rewrite("vector3(1234.567891, 1.0, 5.0)", rotate(90.0));
+        assert_eq!(rewritten, "vector3(-1.000000, 1234.567891, 5.0)");
+    }
+
+    #[test]
+    fn rotate_turns_heading() {
+        let (rewritten, _) = rewrite("vector4(10.0, 0.0, 5.0, 300.0)", rotate(90.0));
+        assert_eq!(rewritten, "vector4(0.0, 10.0, 5.0, 30.0)");
+    }
+
+    #[test]
+    fn unchanged_vectors_are_left_alone() {
+        let content = "a = vector3(+1.50, 2, 3)";
+        let translate = VectorTransform::Translate { offset: vec![0.0, 0.0, 0.0] };
+        assert_eq!(rewrite(content, translate), (content.to_string(), 0));
+
+        let replace = VectorTransform::Replace { vector: vec![1.5] };
+        assert_eq!(rewrite(content, replace).1, 0);
+    }
+
+    #[test]
+    fn only_literals_are_replaced() {
+        let content = "Config.Spots = {\n    vector3(1.0, 2.0, 3.0), -- first\n    vector3(4.0, 5.0, 6.0),\n}\n";
+        let translate = VectorTransform::Translate { offset: vec![1.0, -1.0] };
+        let (rewritten, changes) = rewrite(content, translate);
+        assert_eq!(rewritten, "Config.Spots = {\n    vector3(2.0, 1.0, 3.0), -- first\n    vector3(5.0, 4.0, 6.0),\n}\n");
+        assert_eq!(changes, 2);
+    }
+}
@@ -3,9 +3,34 @@ import { invoke } from "@tauri-apps/api/core";
 import { settings } from "$core/settings.svelte";
 
 const MAX_OUTPUT_LINES = 500;
+
+export type QueuedCommand = {
+    id: number;
+    command: string;
+    running: boolean;
+};
+
+export type CliProgress = {
+    id: number;
+    done: number;
+    total: number;
+    message: string;
+};
+
+export type CliStatus =
+    | { state: "starting" }
+    | { state: "ready" }
+    | { state: "crashed"; code: number | null }
+    | { state: "restarting"; attempt: number; delay_ms: number }
+    | { state: "stopped" };
+
 class CodeWalkerCli {
     output: string[] = $state([]);
     command_history: string[] = $state([]);
+    queue: QueuedCommand[] = $state([]);
+    status: CliStatus = $state({ state: "stopped" });
+    /** Latest progress of running commands by request id; only CLI builds with the JSON protocol send it. */
+    progress: Record<number, CliProgress> = $state({});
     constructor() {
         this.output = [];
     }
@@ -14,6 +39,7 @@ class CodeWalkerCli {
         console.log("Starting CodeWalkerCLI", settings.game_path);
 		invoke("start_codewalker", {
 			gtaPath: settings.game_path,
+			autoRestart: settings.cli_auto_restart,
 		})
 			.then(() => {
                 this.output.push("CodeWalkerCLI started\n");
@@ -41,6 +67,48 @@ class CodeWalkerCli {
             });
     }
     
+    restart() {
+        invoke("restart_codewalker")
+            .then(() => {
+                this.output.push("CodeWalkerCLI restarted\n");
+            })
+            .catch((e) => {
+                toast.add({
+                    text: `Failed to restart CodeWalkerCLI: ${e as string}`,
+                    type: "error",
+                })
+                console.error(e);
+            });
+    }
+
+    set_status(status: CliStatus) {
+        this.status = status;
+        if (status.state === "crashed") {
+            toast.add({
+                text: `CodeWalkerCLI exited unexpectedly (code ${status.code ?? "unknown"})`,
+                type: "error",
+            });
+        }
+    }
+
+    set_queue(queue: QueuedCommand[]) {
+        this.queue = queue;
+        // Drop progress for commands that are no longer running.
+        for (const id of Object.keys(this.progress).map(Number)) {
+            if (!queue.some((queued) => queued.id === id)) {
+                delete this.progress[id];
+            }
+        }
+    }
+
+    async cancel(request_id: number) {
+        try {
+            await invoke("cancel_command", { requestId: request_id });
+        } catch (e) {
+            console.error(e);
+        }
+    }
+
     add_output(text: string) {
         this.output.push(text);
         if (this.output.length > MAX_OUTPUT_LINES) {
@@ -48,12 +116,13 @@ class CodeWalkerCli {
         }
     }
     
-    async send_command(command: string) {
+    async send_command(command: string, timeout_secs?: number) {
         console.log("Sending command:", command);
         this.command_history.push(command);
         try {
             const result = await invoke<string>("send_command", {
-                command: command
+                command: command,
+                timeoutSecs: timeout_secs,
             });
             return result;
         } catch (e) {
@@ -1,6 +1,15 @@
 import { load } from "@tauri-apps/plugin-store";
+import { invoke } from "@tauri-apps/api/core";
 import { toast } from "$core/toast.svelte";
 
+export type ScanConfig = {
+    include: string[];
+    exclude: string[];
+    use_ignore_files: boolean;
+    max_depth: number | null;
+    follow_symlinks: boolean;
+};
+
 //TODO this can prob be done a lot cleaner and easier
 class Settings {
     store: any;
@@ -12,6 +21,10 @@ class Settings {
     base_path = $state("");
     game_path = $state(""); 
     save_path = $state("");
+    /** Folders the backend lets file commands touch. Only changed through the backend's folder picker. */
+    allowed_roots: string[] = $state([]);
+    cli_auto_restart = $state(true);
+    scan_config: ScanConfig | null = $state(null);
 
     constructor() {
         this.initPromise = this.init();
@@ -29,6 +42,10 @@ class Settings {
             this.base_path = await this.store.get("base_path") || this.base_path;
             this.game_path = await this.store.get("game_path") || this.game_path;
             this.save_path = await this.store.get("save_path") || this.save_path;
+            this.cli_auto_restart = await this.store.get("cli_auto_restart") ?? this.cli_auto_restart;
+            this.allowed_roots = await invoke("get_allowed_roots");
+            this.scan_config = await this.store.get("scan_config") || await invoke("get_scan_config");
+            await invoke("set_scan_config", { config: this.scan_config });
             this.ready = true;
             
         } catch (error) {
@@ -41,11 +58,30 @@ class Settings {
         }
     }
         
+    // Folders are picked in a dialog opened by the backend, which then allows them.
+    async pick_folder(): Promise<string | null> {
+        try {
+            const folder: string | null = await invoke("pick_allowed_root");
+            this.allowed_roots = await invoke("get_allowed_roots");
+            return folder;
+        } catch (error) {
+            toast.add({ text: `Can't use this folder: ${error}`, type: "error" });
+            return null;
+        }
+    }
+
+    async remove_root(root: string) {
+        this.allowed_roots = await invoke("remove_allowed_root", { root });
+    }
+
     async save(){
         try {
             await this.store.set("base_path", this.base_path);
             await this.store.set("game_path", this.game_path);
             await this.store.set("save_path", this.save_path);
+            await this.store.set("cli_auto_restart", this.cli_auto_restart);
+            await invoke("set_scan_config", { config: this.scan_config });
+            await this.store.set("scan_config", this.scan_config);
         } catch (error) {
             console.error("Failed to save settings:", error);
         }
@@ -1,6 +1,5 @@
 import { goto } from "$app/navigation";
 import { createPatch, type ParsedDiff, parsePatch } from "diff";
-import { readTextFile } from "@tauri-apps/plugin-fs";
 import { invoke } from "@tauri-apps/api/core";
 import { toast } from "$core/toast.svelte";
 import { codewalkercli } from "$core/codewalkercli.svelte";
@@ -64,7 +63,9 @@ class DiffTool {
 
 			let xml = "";
 			if (ext === "xml") {
-				xml = await readTextFile(file_path);
+				xml = await invoke("read_file", {
+					path: file_path,
+				});
 			} else {
 				const temp_path = `${settings.save_path}\\temp${index}.xml`;
 				const result = await codewalkercli.export_xml(file_path, temp_path);
@@ -379,6 +380,8 @@ class DiffTool {
 					path: new_xml_path,
 					content: this.result,
 				});
+				// The import overwrites the target directly, so keep its current version first.
+				await invoke("backup_file", { path: target_path });
 				const result = await codewalkercli.import_xml(
 					new_xml_path,
 					target_path,
@@ -1,9 +1,37 @@
+export type VectorSyntax =
+	| "vector_call"
+	| "keyed_table"
+	| "coord_array"
+	| "json_object"
+	| "constructor"
+	| "number_array"
+	| "xml_attributes";
+
+export type SourceType = "lua" | "javascript" | "c_sharp" | "json" | "ymap_xml";
+
 export type VectorInfo = {
 	vector: [number, number, number];
 	file: string;
 	line_number: number;
+	column: number;
+	syntax: VectorSyntax;
+	source: SourceType;
+	in_comment: boolean;
+	key_path: string | null;
+	call: string | null;
+	context: string[];
+	distance?: number;
 };
 
+export type VectorRegion =
+	| { type: "sphere"; center: number[]; radius: number }
+	| { type: "box"; min: number[]; max: number[] }
+	| { type: "polygon"; points: [number, number][]; min_z: number | null; max_z: number | null };
+
+export type HeadingMode =
+	| { mode: "ignore" }
+	| { mode: "compare"; heading: number; tolerance: number };
+
 export type FolderTree = {
 	files: { [fileName: string]: VectorInfo[] };
 	subfolders: { [folderName: string]: FolderTree };